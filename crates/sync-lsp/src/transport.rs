@@ -92,6 +92,30 @@ pub fn with_stdio_transport(
     Ok(())
 }
 
+/// Creates an in-memory LSP connection, which is useful for running the
+/// language server in process.
+///
+/// Returns the connection for the server, and the sender and the receiver
+/// for the client side.
+pub fn memory_transport() -> (Connection, Sender<Message>, Receiver<Message>) {
+    let (client_sender, server_receiver) = unbounded::<Message>();
+    let (server_sender, client_receiver) = unbounded::<Message>();
+    let (event_sender, event_receiver) = unbounded::<crate::Event>();
+
+    let connection = Connection {
+        sender: ConnectionTx {
+            event: event_sender,
+            lsp: server_sender,
+        },
+        receiver: ConnectionRx {
+            event: event_receiver,
+            lsp: server_receiver,
+        },
+    };
+
+    (connection, client_sender, client_receiver)
+}
+
 /// Creates an LSP connection via io.
 ///
 /// # Example
@@ -82,6 +82,7 @@ impl DocNewArgs {
             inputs: vec![],
             font_paths,
             system_fonts: !self.font.ignore_system_fonts,
+            font_fallback: self.font.fallback.clone(),
             package_path,
             package_cache_path,
         }
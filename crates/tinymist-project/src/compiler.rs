@@ -466,13 +466,15 @@ impl<F: CompilerFeat + Send + Sync + 'static, Ext: Default + 'static> ProjectCom
     pub fn restart_dedicate(&mut self, group: &str, entry: EntryState) -> Result<ProjectInsId> {
         let id = ProjectInsId(group.into());
 
-        let verse = CompilerUniverse::<F>::new_raw(
+        let mut verse = CompilerUniverse::<F>::new_raw(
             entry,
             Some(self.primary.verse.inputs().clone()),
             self.primary.verse.vfs().fork(),
             self.primary.verse.registry.clone(),
             self.primary.verse.font_resolver.clone(),
         );
+        verse.plugin_policy = self.primary.verse.plugin_policy.clone();
+        verse.font_fallback = self.primary.verse.font_fallback.clone();
 
         let proj = Self::create_project(
             id.clone(),
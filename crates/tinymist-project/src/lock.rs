@@ -276,6 +276,7 @@ impl LockFileUpdate {
             inputs: vec![],
             font_paths,
             system_fonts: true, // !args.font.ignore_system_fonts,
+            font_fallback: world.font_fallback.as_ref().clone(),
             package_path: None,
             package_cache_path: None,
         };
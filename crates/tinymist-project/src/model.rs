@@ -11,6 +11,7 @@ use serde::{Deserialize, Serialize};
 use tinymist_std::error::prelude::*;
 use tinymist_std::path::{unix_slash, PathClean};
 use tinymist_std::{bail, ImmutPath};
+use tinymist_world::font::FontFallback;
 use tinymist_world::vfs::WorkspaceResolver;
 use tinymist_world::{EntryReader, EntryState};
 use typst::diag::EcoString;
@@ -516,6 +517,9 @@ pub struct ProjectInput {
     /// Whether to use system fonts.
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub system_fonts: bool,
+    /// The font families used when the document doesn't specify fonts.
+    #[serde(default, skip_serializing_if = "FontFallback::is_empty")]
+    pub font_fallback: FontFallback,
     /// The project's package path.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub package_path: Option<ResourcePath>,
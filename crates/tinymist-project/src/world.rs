@@ -4,7 +4,7 @@ pub use tinymist_world as base;
 pub use tinymist_world::args::*;
 pub use tinymist_world::config::CompileFontOpts;
 pub use tinymist_world::entry::*;
-pub use tinymist_world::{font, package, vfs};
+pub use tinymist_world::{font, package, plugin, vfs};
 pub use tinymist_world::{
     CompilerUniverse, CompilerWorld, EntryOpts, EntryState, RevisingUniverse, TaskInputs,
 };
@@ -63,7 +63,8 @@ impl WorldProvider for CompileOnceArgs {
             Some(&self.package),
         );
 
-        Ok(LspUniverseBuilder::build(entry, inputs, fonts, package))
+        let verse = LspUniverseBuilder::build(entry, inputs, fonts, package);
+        Ok(verse.with_font_fallback(self.font.fallback.clone()))
     }
 
     fn entry(&self) -> Result<EntryOpts> {
@@ -130,6 +131,7 @@ impl WorldProvider for (ProjectInput, ImmutPath) {
                     .collect::<Vec<_>>()
             },
             ignore_system_fonts: !proj.system_fonts,
+            ..Default::default()
         })?;
         let package = LspUniverseBuilder::resolve_package(
             // todo: recover certificate path
@@ -146,12 +148,13 @@ impl WorldProvider for (ProjectInput, ImmutPath) {
             }),
         );
 
-        Ok(LspUniverseBuilder::build(
+        let verse = LspUniverseBuilder::build(
             entry,
             Arc::new(LazyHash::new(inputs)),
             Arc::new(fonts),
             package,
-        ))
+        );
+        Ok(verse.with_font_fallback(proj.font_fallback.clone()))
     }
 
     fn entry(&self) -> Result<EntryOpts> {
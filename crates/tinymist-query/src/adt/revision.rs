@@ -11,9 +11,10 @@ pub struct RevisionLock {
 
 impl RevisionLock {
     pub fn access(&self, revision: NonZeroUsize) {
-        self.used
-            .set(revision.get())
-            .unwrap_or_else(|_| panic!("revision {revision} is determined"))
+        let used = *self.used.get_or_init(|| revision.get());
+        if used != revision.get() {
+            panic!("revision {used} is determined, but {revision} is accessed");
+        }
     }
 }
 
@@ -76,6 +77,16 @@ impl<T> RevisionManager<T> {
         }
     }
 
+    /// Lock the same revision as an existing lock in *main thread*.
+    #[must_use]
+    pub fn lock_same(&mut self, lock: &RevisionLock) -> RevisionLock {
+        *self.locked.entry(lock.estimated).or_default() += 1;
+        RevisionLock {
+            estimated: lock.estimated,
+            used: lock.used.clone(),
+        }
+    }
+
     /// Find the last revision slot by revision number.
     pub fn find_revision(
         &mut self,
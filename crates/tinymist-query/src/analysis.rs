@@ -24,6 +24,8 @@ pub mod signature;
 pub use signature::*;
 pub mod semantic_tokens;
 pub use semantic_tokens::*;
+pub mod show_rule;
+pub use show_rule::*;
 use tinymist_std::ImmutPath;
 use tinymist_world::vfs::WorkspaceResolver;
 use tinymist_world::WorldDeps;
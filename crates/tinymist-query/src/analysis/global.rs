@@ -201,6 +201,16 @@ pub trait PeriscopeProvider {
     ) -> Option<String> {
         None
     }
+
+    /// Resolve telescope image at where the definition is rendered.
+    fn periscope_definition(
+        &self,
+        _ctx: &mut LocalContext,
+        _doc: VersionedDocument,
+        _def: &Definition,
+    ) -> Option<String> {
+        None
+    }
 }
 
 /// The local context guard that performs gc once dropped.
@@ -1234,6 +1244,18 @@ pub struct AnalysisRevLock {
     grid: Arc<Mutex<AnalysisRevCache>>,
 }
 
+impl AnalysisRevLock {
+    /// Locks the same revision again, e.g. for each request on a snapshot
+    /// pinned by the client.
+    pub fn fork(&self) -> AnalysisRevLock {
+        AnalysisRevLock {
+            inner: self.grid.lock().manager.lock_same(&self.inner),
+            tokens: None,
+            grid: self.grid.clone(),
+        }
+    }
+}
+
 impl Drop for AnalysisRevLock {
     fn drop(&mut self) {
         let mut mu = self.grid.lock();
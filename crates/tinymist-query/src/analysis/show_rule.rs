@@ -0,0 +1,384 @@
+//! Analyze show rules in a source file, to find rules that are overridden by
+//! later rules or that match the output of each other without end.
+//!
+//! Note: a show rule that creates the element it matches, e.g.
+//! `#show heading: it => heading(it.body)`, is not reported, as typst doesn't
+//! apply a show rule to its own output. However, typst doesn't guard against
+//! a cycle between several show rules, e.g. a rule on `heading` creating a
+//! `figure` and a rule on `figure` creating a `heading`.
+
+use super::prelude::*;
+
+/// Get problems of the show rules in a source.
+#[comemo::memoize]
+pub fn get_show_rule_issues(src: &Source) -> Arc<EcoVec<ShowRuleIssue>> {
+    let mut worker = ShowRuleWorker::default();
+    worker.check(src.root());
+    Arc::new(worker.issues)
+}
+
+/// A problem of a show rule.
+#[derive(Debug, Clone)]
+pub struct ShowRuleIssue {
+    /// The span of the problematic show rule.
+    pub span: Span,
+    /// The message of the problem.
+    pub message: EcoString,
+    /// The hints to solve the problem.
+    pub hints: EcoVec<EcoString>,
+    /// The span of the other syntax causing the problem, and its description.
+    pub related: Option<(Span, EcoString)>,
+}
+
+/// A show rule waiting for content to apply to.
+struct PendingShowRule {
+    /// The span of the show rule.
+    span: Span,
+    /// The selector text without whitespaces, e.g. `heading.where(level:1)`.
+    key: EcoString,
+    /// The path to the selected element function, e.g. `heading` in
+    /// `heading.where(level: 1)`.
+    func: Option<EcoString>,
+    /// Whether the selector is an element function without any filter.
+    bare: bool,
+    /// Whether the transform discards the matched element.
+    replacing: bool,
+}
+
+/// A show rule matching all the elements of a function, e.g. `heading` but
+/// not `heading.where(level: 1)`.
+struct BareShowRule {
+    /// The span of the show rule.
+    span: Span,
+    /// The path to the selected element function.
+    func: EcoString,
+    /// The transform of the show rule.
+    transform: SyntaxNode,
+}
+
+#[derive(Default)]
+struct ShowRuleWorker {
+    issues: EcoVec<ShowRuleIssue>,
+}
+
+impl ShowRuleWorker {
+    fn check(&mut self, node: &SyntaxNode) {
+        if matches!(node.kind(), SyntaxKind::Markup | SyntaxKind::Code) {
+            self.check_scope(node);
+        }
+
+        for child in node.children() {
+            self.check(child);
+        }
+    }
+
+    /// Checks show rules that are siblings in a markup or code block.
+    ///
+    /// A show rule applies to the content following it in the block. When two
+    /// show rules match the same element, the later one is applied first. If
+    /// it doesn't keep the matched element in its output, the earlier one has
+    /// nothing to match. Thus an earlier rule followed by such a rule without
+    /// any content in between takes no effect.
+    fn check_scope(&mut self, node: &SyntaxNode) {
+        let mut pending: Vec<PendingShowRule> = vec![];
+        let mut bare_rules = vec![];
+        for child in node.children() {
+            let Some(rule) = child.cast::<ast::ShowRule>() else {
+                if !is_stylistic(child) {
+                    pending.clear();
+                }
+                continue;
+            };
+
+            let Some(selector) = rule.selector() else {
+                // The everything show rule applies to the rest content.
+                pending.clear();
+                continue;
+            };
+
+            let key = compact_text(selector.to_untyped());
+            let (func, bare) = match selector {
+                ast::Expr::Ident(..) | ast::Expr::FieldAccess(..) => (Some(key.clone()), true),
+                ast::Expr::FuncCall(call) => match call.callee() {
+                    ast::Expr::FieldAccess(access) if access.field().get() == "where" => {
+                        (Some(compact_text(access.target().to_untyped())), false)
+                    }
+                    _ => (None, false),
+                },
+                _ => (None, false),
+            };
+
+            let transform = rule.transform();
+            if let (Some(func), true) = (&func, bare) {
+                if !matches!(transform, ast::Expr::Set(..)) {
+                    bare_rules.push(BareShowRule {
+                        span: child.span(),
+                        func: func.clone(),
+                        transform: transform.to_untyped().clone(),
+                    });
+                }
+            }
+
+            let next = PendingShowRule {
+                span: child.span(),
+                key,
+                func,
+                bare,
+                replacing: is_replacing(transform),
+            };
+
+            if next.replacing {
+                pending.retain(|prev| {
+                    let overridden = prev.key == next.key
+                        || (next.bare && next.func.is_some() && next.func == prev.func);
+                    if overridden {
+                        self.issues.push(overridden_issue(prev, &next));
+                    }
+                    !overridden
+                });
+            }
+            pending.push(next);
+        }
+
+        self.check_cycles(&bare_rules);
+    }
+
+    /// Checks bare show rules that are siblings in a markup or code block,
+    /// which create the elements matched by each other in a cycle. All of them
+    /// are in effect on the content following the last one, on which the rules
+    /// are applied to the output of each other without end.
+    fn check_cycles(&mut self, rules: &[BareShowRule]) {
+        // The rules matching the elements created by each rule, and the calls
+        // creating them.
+        let edges: Vec<Vec<(usize, Span)>> = rules
+            .iter()
+            .map(|rule| {
+                let targets = rules.iter().enumerate();
+                // typst guards against the rules on the same element function.
+                let targets = targets.filter(|(_, target)| target.func != rule.func);
+                targets
+                    .filter_map(|(idx, target)| {
+                        Some((idx, find_call(&rule.transform, &target.func)?))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        for start in 0..rules.len() {
+            // Each cycle is reported once, at the earliest rule in it.
+            let mut visited = vec![false; rules.len()];
+            let mut path = vec![];
+            if !find_cycle(&edges, start, start, &mut visited, &mut path) {
+                continue;
+            }
+
+            let rule = &rules[start];
+            let chain = std::iter::once(start).chain(path.iter().map(|(idx, _)| *idx));
+            let chain = chain.map(|idx| eco_format!("`{}`", rules[idx].func));
+            let chain = chain.collect::<Vec<_>>().join(" -> ");
+            let (next, call) = path[0];
+            let next = &rules[next].func;
+
+            self.issues.push(ShowRuleIssue {
+                span: rule.span,
+                message: eco_format!(
+                    "show rule on `{}` matches the output of other show rules without end",
+                    rule.func
+                ),
+                hints: eco_vec![
+                    eco_format!(
+                        "the show rules create the elements matched by each other in a cycle: \
+                         {chain}"
+                    ),
+                    eco_format!(
+                        "narrow a selector, e.g. with `{}.where(..)`, or return the matched \
+                         element instead of creating a new one",
+                        rule.func
+                    ),
+                ],
+                related: Some((call, eco_format!("a new `{next}` is created here"))),
+            });
+        }
+    }
+}
+
+/// Finds a path from `node` back to `start` through the rules after `start`,
+/// and pushes the rules on the path and the calls creating their elements.
+fn find_cycle(
+    edges: &[Vec<(usize, Span)>],
+    start: usize,
+    node: usize,
+    visited: &mut [bool],
+    path: &mut Vec<(usize, Span)>,
+) -> bool {
+    for &(next, call) in &edges[node] {
+        if next == start {
+            path.push((next, call));
+            return true;
+        }
+        if next < start || visited[next] {
+            continue;
+        }
+
+        visited[next] = true;
+        path.push((next, call));
+        if find_cycle(edges, start, next, visited, path) {
+            return true;
+        }
+        path.pop();
+    }
+
+    false
+}
+
+fn overridden_issue(prev: &PendingShowRule, next: &PendingShowRule) -> ShowRuleIssue {
+    ShowRuleIssue {
+        span: prev.span,
+        message: eco_format!(
+            "show rule on `{}` has no effect, as it is overridden by a later show rule on `{}`",
+            prev.key,
+            next.key
+        ),
+        hints: eco_vec![
+            "show rules are applied from the latest to the earliest, so the later rule \
+             replaces the matched element before this rule can match it"
+                .into(),
+            "keep the matched element in the output of the later rule, e.g. \
+             `it => [... #it ...]`, to apply both rules"
+                .into(),
+        ],
+        related: Some((
+            next.span,
+            "the later show rule, which is applied first".into(),
+        )),
+    }
+}
+
+/// Whether the syntax node doesn't produce content, i.e. a show rule before
+/// it can't take effect on it.
+fn is_stylistic(node: &SyntaxNode) -> bool {
+    matches!(
+        node.kind(),
+        SyntaxKind::Space
+            | SyntaxKind::Parbreak
+            | SyntaxKind::LineComment
+            | SyntaxKind::BlockComment
+            | SyntaxKind::Hash
+            | SyntaxKind::Semicolon
+            | SyntaxKind::LetBinding
+            | SyntaxKind::SetRule
+            | SyntaxKind::ModuleImport
+    )
+}
+
+/// Whether the transform of a show rule discards the matched element.
+fn is_replacing(transform: ast::Expr) -> bool {
+    match transform {
+        ast::Expr::Content(..) | ast::Expr::Str(..) | ast::Expr::None(..) => true,
+        ast::Expr::Closure(closure) => match closure.params().children().next() {
+            Some(ast::Param::Pos(ast::Pattern::Placeholder(..))) => true,
+            Some(ast::Param::Pos(ast::Pattern::Normal(ast::Expr::Ident(param)))) => {
+                !mentions(closure.body().to_untyped(), param.get())
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Whether the syntax node refers to a name.
+fn mentions(node: &SyntaxNode, name: &str) -> bool {
+    if matches!(node.kind(), SyntaxKind::Ident | SyntaxKind::MathIdent) {
+        return node.text() == name;
+    }
+
+    node.children().any(|child| mentions(child, name))
+}
+
+/// Finds a call to the function in the syntax node.
+fn find_call(node: &SyntaxNode, func: &str) -> Option<Span> {
+    if let Some(call) = node.cast::<ast::FuncCall>() {
+        let callee = call.callee();
+        if matches!(callee, ast::Expr::Ident(..) | ast::Expr::FieldAccess(..))
+            && compact_text(callee.to_untyped()) == func
+        {
+            return Some(callee.span());
+        }
+    }
+
+    node.children().find_map(|child| find_call(child, func))
+}
+
+/// Gets the text of the syntax node without whitespaces.
+fn compact_text(node: &SyntaxNode) -> EcoString {
+    node.clone()
+        .into_text()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issues(text: &str) -> Vec<String> {
+        let source = Source::detached(text);
+        get_show_rule_issues(&source)
+            .iter()
+            .map(|issue| issue.message.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn overridden() {
+        let res = issues("#show heading: it => emph(it)\n#show heading: [Title]\n= A");
+        assert_eq!(res.len(), 1, "{res:?}");
+        assert!(res[0].contains("has no effect"), "{res:?}");
+        let res = issues("#show heading.where(level: 1): emph\n#show heading: none\n= A");
+        assert_eq!(res.len(), 1, "{res:?}");
+    }
+
+    #[test]
+    fn not_overridden() {
+        // the later rule keeps the element
+        assert!(issues("#show heading: emph\n#show heading: it => [#it]\n= A").is_empty());
+        // the earlier rule takes effect on the content in between
+        assert!(issues("#show heading: emph\n= A\n#show heading: none\n= B").is_empty());
+        // the later rule is more specific
+        assert!(issues("#show heading: emph\n#show heading.where(level: 1): none").is_empty());
+        // show-set rules compose
+        assert!(issues("#show heading: emph\n#show heading: set text(red)").is_empty());
+    }
+
+    #[test]
+    fn self_matching() {
+        // typst doesn't apply a show rule to its own output
+        assert!(issues("#show heading: it => heading(it.body)\n= A").is_empty());
+    }
+
+    #[test]
+    fn cycle() {
+        let res = issues(
+            "#show heading: it => figure(it.body)\n#show figure: it => heading(it.body)\n= A",
+        );
+        assert_eq!(res.len(), 1, "{res:?}");
+        assert!(res[0].contains("without end"), "{res:?}");
+        let res = issues(
+            "#show heading: it => emph(it.body)\n#show emph: it => strong(it.body)\n\
+             #show strong: it => heading(it.body)\n= A",
+        );
+        assert_eq!(res.len(), 1, "{res:?}");
+    }
+
+    #[test]
+    fn not_cycle() {
+        // the created element is not matched by the filtered rule
+        let text = "#show heading: it => figure(it.body)\n\
+                    #show figure.where(kind: image): it => heading(it.body)\n= A";
+        assert!(issues(text).is_empty());
+        // show-set rules don't create elements
+        let text = "#show heading: it => figure(it.body)\n#show figure: set text(red)\n= A";
+        assert!(issues(text).is_empty());
+    }
+}
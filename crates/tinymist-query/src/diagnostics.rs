@@ -1,4 +1,5 @@
 use tinymist_project::LspWorld;
+use tinymist_world::plugin::plugin_calls;
 use typst::syntax::Span;
 
 use crate::{prelude::*, LspWorldExt};
@@ -53,6 +54,190 @@ pub fn convert_diagnostics<'a>(
     lookup
 }
 
+/// Converts the syntax errors in the source files to LSP diagnostics. This
+/// only parses the sources, so it is available long before the compilation
+/// finishes.
+pub fn convert_syntax_errors(
+    world: &LspWorld,
+    ids: impl IntoIterator<Item = TypstFileId>,
+    position_encoding: PositionEncoding,
+) -> DiagnosticsMap {
+    let mut lookup = DiagnosticsMap::new();
+    for id in ids {
+        if id.vpath().as_rooted_path().extension() != Some("typ".as_ref()) {
+            continue;
+        }
+        let (Ok(uri), Ok(source)) = (world.uri_for_id(id), world.source(id)) else {
+            continue;
+        };
+        if !source.root().erroneous() {
+            continue;
+        }
+
+        for error in source.root().errors() {
+            let diagnostic = Diagnostic {
+                range: diagnostic_range(&source, error.span, position_encoding),
+                severity: Some(DiagnosticSeverity::ERROR),
+                message: format!("{}{}", error.message, diagnostic_hints(&error.hints)),
+                source: Some("typst".to_owned()),
+                related_information: Some(vec![]),
+                ..Default::default()
+            };
+            lookup.entry(uri.clone()).or_default().push(diagnostic);
+        }
+    }
+
+    lookup
+}
+
+/// Whether the Typst diagnostic is a syntax error, which is also reported by
+/// [`convert_syntax_errors`].
+pub fn is_syntax_error(world: &LspWorld, diagnostic: &TypstDiagnostic) -> bool {
+    let Some(id) = diagnostic.span.id() else {
+        return false;
+    };
+    let Ok(source) = world.source(id) else {
+        return false;
+    };
+
+    source
+        .find(diagnostic.span)
+        .is_some_and(|node| node.kind() == SyntaxKind::Error)
+}
+
+/// Checks the show rules in the source files and converts the found problems
+/// to LSP diagnostics. Files in packages are not checked.
+pub fn convert_show_rule_issues(
+    world: &LspWorld,
+    ids: impl IntoIterator<Item = TypstFileId>,
+    position_encoding: PositionEncoding,
+) -> DiagnosticsMap {
+    let mut lookup = DiagnosticsMap::new();
+    for id in ids {
+        let is_typst = id.vpath().as_rooted_path().extension() == Some("typ".as_ref());
+        if id.package().is_some() || !is_typst {
+            continue;
+        }
+        let (Ok(uri), Ok(source)) = (world.uri_for_id(id), world.source(id)) else {
+            continue;
+        };
+
+        for issue in crate::analysis::get_show_rule_issues(&source).iter() {
+            let related = issue.related.iter().flat_map(|(span, message)| {
+                let range = source.range(*span)?;
+                Some(DiagnosticRelatedInformation {
+                    location: LspLocation {
+                        uri: uri.clone(),
+                        range: to_lsp_range(range, &source, position_encoding),
+                    },
+                    message: message.to_string(),
+                })
+            });
+
+            let diagnostic = Diagnostic {
+                range: diagnostic_range(&source, issue.span, position_encoding),
+                severity: Some(DiagnosticSeverity::WARNING),
+                message: format!("{}{}", issue.message, diagnostic_hints(&issue.hints)),
+                source: Some("tinymist".to_owned()),
+                related_information: Some(related.collect()),
+                ..Default::default()
+            };
+            lookup.entry(uri.clone()).or_default().push(diagnostic);
+        }
+    }
+
+    lookup
+}
+
+/// Reports the plugins replaced by stubs in the untrusted workspace as LSP
+/// diagnostics at the `plugin(..)` calls loading them.
+pub fn convert_plugin_stubs(
+    world: &LspWorld,
+    ids: impl IntoIterator<Item = TypstFileId>,
+    position_encoding: PositionEncoding,
+) -> DiagnosticsMap {
+    let mut lookup = DiagnosticsMap::new();
+    if !world.plugin_policy.is_stub() {
+        return lookup;
+    }
+
+    let ids = ids.into_iter().collect::<Vec<_>>();
+    let is_source =
+        |id: &&TypstFileId| id.vpath().as_rooted_path().extension() == Some("typ".as_ref());
+    for source in ids
+        .iter()
+        .filter(is_source)
+        .flat_map(|id| world.source(*id).ok())
+    {
+        let Ok(uri) = world.uri_for_id(source.id()) else {
+            continue;
+        };
+
+        // The plugins loaded by the compilation are among the dependencies.
+        let calls = plugin_calls(&source);
+        for (id, span) in calls.iter().filter(|(id, _)| ids.contains(id)) {
+            let path = id.vpath().as_rooted_path();
+            let plugin = match id.package() {
+                Some(spec) => format!("{spec}{}", path.display()),
+                None => path.display().to_string(),
+            };
+            let message = format!(
+                "plugin {plugin} is not executed, since the workspace is not trusted{}",
+                diagnostic_hints(&[
+                    "functions of the plugin return empty bytes instead".into(),
+                    "trust the workspace to execute the plugin".into(),
+                ])
+            );
+
+            let diagnostic = Diagnostic {
+                range: diagnostic_range(&source, *span, position_encoding),
+                severity: Some(DiagnosticSeverity::WARNING),
+                message,
+                source: Some("tinymist".to_owned()),
+                ..Default::default()
+            };
+            lookup.entry(uri.clone()).or_default().push(diagnostic);
+        }
+    }
+
+    lookup
+}
+
+/// Reports the families in the font fallback that are not found as LSP
+/// diagnostics on the main file.
+pub fn convert_font_fallback_issues(
+    world: &LspWorld,
+    position_encoding: PositionEncoding,
+) -> DiagnosticsMap {
+    let mut lookup = DiagnosticsMap::new();
+    let missing = world.font_fallback.missing(world.book());
+    if missing.is_empty() {
+        return lookup;
+    }
+    let (Ok(uri), Ok(source)) = (world.uri_for_id(world.main()), world.source(world.main())) else {
+        return lookup;
+    };
+
+    for family in missing {
+        let diagnostic = Diagnostic {
+            range: to_lsp_range(0..0, &source, position_encoding),
+            severity: Some(DiagnosticSeverity::WARNING),
+            message: format!(
+                "font family `{family}` in the font fallback is not found{}",
+                diagnostic_hints(&[
+                    "the next family in the font fallback is used instead".into(),
+                    "install the font, or add the path to the font to `fontPaths`".into(),
+                ])
+            ),
+            source: Some("tinymist".to_owned()),
+            ..Default::default()
+        };
+        lookup.entry(uri.clone()).or_default().push(diagnostic);
+    }
+
+    lookup
+}
+
 fn convert_diagnostic(
     ctx: &LocalDiagContext,
     typst_diagnostic: &TypstDiagnostic,
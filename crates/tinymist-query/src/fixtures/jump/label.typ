@@ -0,0 +1,11 @@
+/// compile: true
+/// page: 2
+/// rendered: 1.
+
+#set heading(numbering: "1.")
+
+#pagebreak()
+
+= Labeled <title_label>
+
+/* position after */ @title_label
@@ -0,0 +1,13 @@
+/// path: base.typ
+#let f() = [Hello]
+-----
+/// compile: true
+/// page: 2
+/// rendered: Hello
+
+#import "base.typ": f
+
+#pagebreak()
+
+#let g = /* position after */ f
+#g()
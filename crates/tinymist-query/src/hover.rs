@@ -3,7 +3,7 @@ use core::fmt::{self, Write};
 use typst::foundations::repr::separated_list;
 use typst_shim::syntax::LinkedNodeExt;
 
-use crate::analysis::get_link_exprs_in;
+use crate::analysis::{get_link_exprs_in, Definition};
 use crate::jump_from_cursor;
 use crate::prelude::*;
 use crate::upstream::{route_of_value, truncated_repr, Tooltip};
@@ -44,6 +44,7 @@ impl StatefulRequest for HoverRequest {
             source,
             doc,
             cursor,
+            target: None,
             def: Default::default(),
             value: Default::default(),
             preview: Default::default(),
@@ -82,6 +83,7 @@ struct HoverWorker<'a> {
     source: Source,
     doc: Option<VersionedDocument>,
     cursor: usize,
+    target: Option<Definition>,
     def: Vec<String>,
     value: Vec<String>,
     preview: Vec<String>,
@@ -123,6 +125,7 @@ impl HoverWorker<'_> {
         let def = self
             .ctx
             .def_of_syntax(&self.source, self.doc.as_ref(), syntax.clone())?;
+        self.target = Some(def.clone());
 
         use Decl::*;
         match def.decl.as_ref() {
@@ -250,6 +253,19 @@ impl HoverWorker<'_> {
         // Preview results
         let provider = self.ctx.analysis.periscope.clone()?;
         let doc = self.doc.as_ref()?;
+
+        // A reference, or a definition in other files, is previewed at where it
+        // is rendered rather than around the cursor.
+        if let Some(def) = self.target.as_ref().filter(|def| {
+            matches!(def.decl.kind(), DefKind::Reference)
+                || def.decl.file_id() != Some(self.source.id())
+        }) {
+            if let Some(content) = provider.periscope_definition(self.ctx, doc.clone(), def) {
+                self.preview.push(content);
+                return Some(());
+            }
+        }
+
         let position = jump_from_cursor(&doc.document, &self.source, self.cursor);
         let position = position.or_else(|| {
             for idx in 1..100 {
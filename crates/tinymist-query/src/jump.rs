@@ -1,14 +1,19 @@
 //! Jumping from and to source and the rendered document.
 
 use std::num::NonZeroUsize;
+use std::ops::Range;
 
 use tinymist_std::typst::TypstDocument;
 use typst::{
+    foundations::{Label, Selector, Value},
     layout::{Frame, FrameItem, Point, Position},
     syntax::{LinkedNode, Source, Span, SyntaxKind},
 };
 use typst_shim::syntax::LinkedNodeExt;
 
+use crate::analysis::{Definition, SharedContext};
+use crate::syntax::Decl;
+
 /// Find the output location in the document for a cursor position.
 pub fn jump_from_cursor(
     document: &TypstDocument,
@@ -52,6 +57,107 @@ pub fn jump_from_cursor(
     }
 }
 
+/// Find the output location in the document where a definition is rendered.
+///
+/// A label or a reference is located at the labelled element. Other
+/// definitions are located at the first glyph laid out from the syntax node
+/// defining them, which is not necessarily in the file being edited.
+pub fn jump_from_definition(
+    ctx: &SharedContext,
+    document: &TypstDocument,
+    def: &Definition,
+) -> Option<Position> {
+    let introspector = document.introspector();
+    if let Some(Value::Content(elem)) = def.value() {
+        if let Some(loc) = elem.location() {
+            return Some(introspector.position(loc));
+        }
+    }
+    if let Decl::Label(..) = def.decl.as_ref() {
+        let elem = introspector.query_first(&Selector::Label(Label::new(def.name())))?;
+        return Some(introspector.position(elem.location()?));
+    }
+
+    let fid = def.decl.file_id()?;
+    let span = def.decl.span();
+    if span.is_detached() {
+        return None;
+    }
+    let source = ctx.source_by_id(fid).ok()?;
+    let range = definition_range(&source, span)?;
+
+    match document {
+        TypstDocument::Paged(paged_doc) => {
+            let mut in_def = |span: Span| {
+                span.id() == Some(fid)
+                    && source
+                        .range(span)
+                        .is_some_and(|r| range.start <= r.start && r.end <= range.end)
+            };
+            for (idx, page) in paged_doc.pages.iter().enumerate() {
+                if let Some(point) = find_first_in_frame(&page.frame, &mut in_def) {
+                    return Some(Position {
+                        page: NonZeroUsize::new(idx + 1)?,
+                        point,
+                    });
+                }
+            }
+
+            None
+        }
+    }
+}
+
+/// Gets the range of the syntax node defining the declaration at the span,
+/// e.g. the whole let binding of a variable.
+fn definition_range(source: &Source, span: Span) -> Option<Range<usize>> {
+    let node = LinkedNode::new(source.root()).find(span)?;
+    let mut ancestor = node.parent();
+    while let Some(parent) = ancestor {
+        match parent.kind() {
+            SyntaxKind::LetBinding => return Some(parent.range()),
+            SyntaxKind::Markup | SyntaxKind::Code | SyntaxKind::Math => break,
+            _ => ancestor = parent.parent(),
+        }
+    }
+
+    Some(node.range())
+}
+
+/// Find the position of the first glyph, shape, or image in a frame whose span
+/// is accepted by `f`.
+fn find_first_in_frame(frame: &Frame, f: &mut impl FnMut(Span) -> bool) -> Option<Point> {
+    for (pos, item) in frame.items() {
+        let span = match item {
+            FrameItem::Group(group) => {
+                // TODO: Handle transformation.
+                if let Some(point) = find_first_in_frame(&group.frame, f) {
+                    return Some(point + *pos);
+                }
+                continue;
+            }
+            FrameItem::Text(text) => {
+                let mut pos = *pos;
+                for glyph in &text.glyphs {
+                    if f(glyph.span.0) {
+                        return Some(pos);
+                    }
+                    pos.x += glyph.x_advance.at(text.size);
+                }
+                continue;
+            }
+            FrameItem::Shape(_, span) | FrameItem::Image(_, _, span) => *span,
+            _ => continue,
+        };
+
+        if f(span) {
+            return Some(*pos);
+        }
+    }
+
+    None
+}
+
 /// Find the position of a span in a frame.
 fn find_in_frame(frame: &Frame, span: Span, min_dis: &mut u64, res: &mut Point) -> Option<Point> {
     for (mut pos, item) in frame.items() {
@@ -81,3 +187,58 @@ fn find_in_frame(frame: &Frame, span: Span, min_dis: &mut u64, res: &mut Point)
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::find_module_level_docs;
+    use crate::tests::*;
+
+    #[test]
+    fn test_from_definition() {
+        snapshot_testing("jump", &|ctx, path| {
+            let source = ctx.source_by_path(&path).unwrap();
+
+            let docs = find_module_level_docs(&source).unwrap_or_default();
+            let properties = get_test_properties(&docs);
+            let doc = compile_doc_for_test(ctx, &properties).unwrap();
+
+            let syntax = ctx.classify_for_decl(&source, find_test_position(&source));
+            let def = ctx.def_of_syntax(&source, Some(&doc), syntax.unwrap());
+            let def = def.unwrap_or_else(|| panic!("no definition in {path:?}"));
+            let position = jump_from_definition(ctx.shared(), &doc.document, &def);
+            let position = position.unwrap_or_else(|| panic!("no position in {path:?}"));
+
+            let page = properties["page"].parse::<usize>().unwrap();
+            assert_eq!(position.page.get(), page, "{path:?}");
+
+            // The definition is located around where the text is rendered.
+            let TypstDocument::Paged(paged_doc) = &doc.document;
+            let rendered = properties["rendered"];
+            let point = find_text(&paged_doc.pages[page - 1].frame, rendered);
+            let point = point.unwrap_or_else(|| panic!("{rendered:?} is not rendered"));
+            let distance = (position.point - point).hypot().to_pt();
+            assert!(
+                distance < 20.,
+                "{path:?}: {position:?} is far from {point:?}"
+            );
+        });
+    }
+
+    /// Finds the position of the first text item starting with the text.
+    fn find_text(frame: &Frame, text: &str) -> Option<Point> {
+        for (pos, item) in frame.items() {
+            match item {
+                FrameItem::Group(group) => {
+                    if let Some(point) = find_text(&group.frame, text) {
+                        return Some(point + *pos);
+                    }
+                }
+                FrameItem::Text(item) if item.text.starts_with(text) => return Some(*pos),
+                _ => {}
+            }
+        }
+
+        None
+    }
+}
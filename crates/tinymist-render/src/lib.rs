@@ -9,7 +9,8 @@ use core::fmt;
 
 use base64::Engine;
 use reflexo_vec2svg::{ExportFeature, SvgExporter, SvgText};
-use tinymist_query::{FramePosition, LocalContext, VersionedDocument};
+use tinymist_query::analysis::Definition;
+use tinymist_query::{jump_from_definition, FramePosition, LocalContext, VersionedDocument};
 use tinymist_std::typst::TypstDocument;
 
 struct PeriscopeExportFeature {}
@@ -91,6 +92,19 @@ impl PeriscopeRenderer {
         )))
     }
 
+    /// Render the periscope image at where the definition is rendered in the
+    /// given document into markdown format.
+    pub fn render_definition_marked(
+        &self,
+        ctx: &mut LocalContext,
+        doc: VersionedDocument,
+        def: &Definition,
+    ) -> Option<String> {
+        let pos = jump_from_definition(ctx.shared(), &doc.document, def)?;
+        log::debug!("periscope definition {:?} at {pos:?}", def.name());
+        self.render_marked(ctx, doc, pos)
+    }
+
     /// Render the periscope image for the given document.
     pub fn render(
         &self,
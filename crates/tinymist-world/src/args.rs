@@ -10,6 +10,7 @@ use tinymist_std::{bail, error::prelude::*};
 use tinymist_vfs::ImmutDict;
 use typst::{foundations::IntoValue, utils::LazyHash};
 
+use crate::font::FontFallback;
 use crate::EntryOpts;
 
 const ENV_PATH_SEP: char = if cfg!(windows) { ';' } else { ':' };
@@ -32,6 +33,11 @@ pub struct CompileFontArgs {
     /// `--font-path`
     #[clap(long, default_value = "false")]
     pub ignore_system_fonts: bool,
+
+    /// The font families used when the document doesn't specify fonts.
+    #[clap(flatten)]
+    #[serde(default, skip_serializing_if = "FontFallback::is_empty")]
+    pub fallback: FontFallback,
 }
 
 /// Arguments related to where packages are stored in the system.
@@ -170,7 +176,8 @@ impl CompileOnceArgs {
             Some(&self.package),
         );
 
-        Ok(SystemUniverseBuilder::build(entry, inputs, fonts, package))
+        let verse = SystemUniverseBuilder::build(entry, inputs, fonts, package);
+        Ok(verse.with_font_fallback(self.font.fallback.clone()))
     }
 }
 
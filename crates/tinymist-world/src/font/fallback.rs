@@ -0,0 +1,113 @@
+//! The fallback chain of font families, which is applied to documents not
+//! specifying fonts.
+
+use clap::ArgAction;
+use ecow::EcoString;
+use serde::{Deserialize, Serialize};
+use typst::foundations::{Regex, StyleChain};
+use typst::text::{Covers, FontBook, FontFamily, FontList, TextElem};
+
+/// The font families used when the document doesn't specify fonts, in the
+/// order of priority per script.
+///
+/// For example, with the `latin` families `["Inria Serif"]` and the `cjk`
+/// families `["Noto Serif CJK SC"]`, latin characters are rendered in Inria
+/// Serif and CJK characters are rendered in Noto Serif CJK SC, regardless of
+/// the fonts installed on the machine.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, clap::Args, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FontFallback {
+    /// The font families for latin characters, used when the document doesn't
+    /// specify fonts
+    #[clap(long = "font-fallback-latin", value_name = "FAMILY", action = ArgAction::Append)]
+    pub latin: Vec<EcoString>,
+    /// The font families for CJK characters, used when the document doesn't
+    /// specify fonts
+    #[clap(long = "font-fallback-cjk", value_name = "FAMILY", action = ArgAction::Append)]
+    pub cjk: Vec<EcoString>,
+    /// The font families for emoji, used when the document doesn't specify
+    /// fonts
+    #[clap(long = "font-fallback-emoji", value_name = "FAMILY", action = ArgAction::Append)]
+    pub emoji: Vec<EcoString>,
+}
+
+impl FontFallback {
+    /// Whether no family is specified.
+    pub fn is_empty(&self) -> bool {
+        self.latin.is_empty() && self.cjk.is_empty() && self.emoji.is_empty()
+    }
+
+    /// Gets the families to set as the default `text.font`.
+    ///
+    /// The latin families come first but don't cover the punctuations shared
+    /// with CJK, then the emoji families cover the pictographs, and the CJK
+    /// families cover the rest. The default families of typst are kept at the
+    /// end for the characters not covered by the chain.
+    pub fn font_list(&self) -> FontList {
+        let emoji = Regex::new(r"\p{Extended_Pictographic}").expect("invalid emoji regex");
+
+        let latin = self
+            .latin
+            .iter()
+            .map(|family| FontFamily::with_coverage(family, Some(Covers::LatinInCjk)));
+        let emoji = self
+            .emoji
+            .iter()
+            .map(|family| FontFamily::with_coverage(family, Some(Covers::Regex(emoji.clone()))));
+        let cjk = self.cjk.iter().map(|family| FontFamily::new(family));
+        let defaults = TextElem::font_in(StyleChain::default()).clone();
+
+        FontList(latin.chain(emoji).chain(cjk).chain(defaults.0).collect())
+    }
+
+    /// Gets the families that are not found in the font book.
+    pub fn missing(&self, book: &FontBook) -> Vec<EcoString> {
+        let families = self.latin.iter().chain(&self.emoji).chain(&self.cjk);
+        families
+            .filter(|family| book.select_family(&family.to_lowercase()).next().is_none())
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_font_list() {
+        let fallback = FontFallback {
+            latin: vec!["Inria Serif".into()],
+            cjk: vec!["Noto Serif CJK SC".into(), "Source Han Serif".into()],
+            emoji: vec!["Noto Color Emoji".into()],
+        };
+
+        let list = fallback.font_list();
+        let names: Vec<_> = list
+            .0
+            .iter()
+            .map(|family| family.as_str().to_owned())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "inria serif",
+                "noto color emoji",
+                "noto serif cjk sc",
+                "source han serif",
+                "libertinus serif",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing() {
+        let fallback = FontFallback {
+            latin: vec!["Inria Serif".into()],
+            ..Default::default()
+        };
+
+        assert!(FontFallback::default().missing(&FontBook::new()).is_empty());
+        assert_eq!(fallback.missing(&FontBook::new()), ["Inria Serif"]);
+    }
+}
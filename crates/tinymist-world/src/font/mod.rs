@@ -23,3 +23,6 @@ pub use resolver::*;
 
 pub(crate) mod partial_book;
 pub use partial_book::*;
+
+pub(crate) mod fallback;
+pub use fallback::*;
@@ -17,6 +17,7 @@ pub use world::*;
 pub mod font;
 pub mod package;
 pub mod parser;
+pub mod plugin;
 
 pub use tinymist_vfs as vfs;
 
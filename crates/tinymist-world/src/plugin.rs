@@ -0,0 +1,615 @@
+//! Policies to execute WebAssembly plugins loaded by `plugin()`.
+//!
+//! The policy is applied to the content of plugin files before typst loads
+//! them, so that it works without any support from the typst compiler:
+//! + A stub module replaces the plugin, whose functions return empty bytes
+//!   without executing any code of the plugin. Plugin functions are only
+//!   declared to return bytes, so the empty bytes are the placeholders of the
+//!   declared type. However, they are not valid values of the formats encoded
+//!   in the bytes, e.g. CBOR, so a package decoding the results may fail.
+//! + The plugin is rewritten to trap once it has run out of its budget. The
+//!   memory is capped by the maximum size of its memories. A plugin cannot be
+//!   interrupted by a timer, so the execution time is bounded by counting the
+//!   loop iterations and function calls instead.
+//!
+//! Typst reads plugins like any other file, so the files loaded by `plugin()`
+//! calls with literal paths, found by [`plugin_calls`], are taken as plugins.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use ecow::{eco_format, EcoString};
+use typst::foundations::Bytes;
+use typst::syntax::{ast, FileId, Source, Span, SyntaxNode};
+use wasmparser_nostd::{
+    BinaryReaderError, ExternalKind, FunctionBody, MemoryType, Operator, Parser, Payload, Type,
+    TypeRef,
+};
+
+/// The size of a page of WebAssembly memory.
+const PAGE_SIZE: u64 = 64 * 1024;
+
+const FUNCTION_SECTION: u8 = 3;
+const MEMORY_SECTION: u8 = 5;
+const GLOBAL_SECTION: u8 = 6;
+const EXPORT_SECTION: u8 = 7;
+const CODE_SECTION: u8 = 10;
+
+/// The policy to execute WebAssembly plugins.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum PluginPolicy {
+    /// Executes plugins without any restriction.
+    #[default]
+    Allow,
+    /// Executes plugins within the budget.
+    Budget(PluginBudget),
+    /// Replaces plugins by stubs, whose functions return empty bytes.
+    Stub,
+}
+
+/// The budget of resources that a plugin can use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PluginBudget {
+    /// The maximum size of the memory in bytes.
+    pub max_memory: Option<u64>,
+    /// The maximum steps of a call to the plugin, where a step is a loop
+    /// iteration or a function call.
+    pub max_steps: Option<u64>,
+}
+
+impl PluginPolicy {
+    /// Whether the plugins are replaced by stubs.
+    pub fn is_stub(&self) -> bool {
+        matches!(self, Self::Stub)
+    }
+
+    /// Applies the policy to the content of a plugin file.
+    pub fn apply(&self, wasm: Bytes) -> Result<Bytes, EcoString> {
+        match self {
+            Self::Allow => Ok(wasm),
+            Self::Budget(budget) => budget.instrument(&wasm).map(Bytes::from),
+            Self::Stub => stub_module(&wasm).map(Bytes::from),
+        }
+    }
+}
+
+impl PluginBudget {
+    /// Rewrites the module to trap once it runs out of the budget.
+    fn instrument(&self, wasm: &[u8]) -> Result<Vec<u8>, EcoString> {
+        if self.max_memory.is_none() && self.max_steps.is_none() {
+            return Ok(wasm.to_vec());
+        }
+
+        let info = ModuleInfo::parse(wasm)?;
+        let steps = self
+            .max_steps
+            .map(|steps| i64::try_from(steps).unwrap_or(i64::MAX));
+
+        // The counter of the remaining steps is appended to the globals.
+        let counter = info.imported_globals + info.globals;
+        let mut check = vec![];
+        // if (counter == 0) unreachable; counter -= 1
+        check.push(0x23);
+        write_uleb(&mut check, counter.into());
+        check.extend([0x50, 0x04, 0x40, 0x00, 0x0b, 0x23]);
+        write_uleb(&mut check, counter.into());
+        check.extend([0x42, 0x01, 0x7d, 0x24]);
+        write_uleb(&mut check, counter.into());
+
+        // Redirects the exported functions to wrappers resetting the counter,
+        // so that each call to the plugin has the full budget.
+        let mut exports = info.exports.clone();
+        let mut wrappers = vec![];
+        if steps.is_some() {
+            let defined = info.func_types.len() as u32;
+            for (_, kind, index) in exports.iter_mut() {
+                if *kind == ExternalKind::Func && *index >= info.imported_funcs {
+                    wrappers.push(*index);
+                    *index = defined + wrappers.len() as u32 - 1;
+                }
+            }
+        }
+
+        let mut out = wasm[..8].to_vec();
+        let mut globals_written = steps.is_none();
+        for (id, range) in &info.sections {
+            let content = &wasm[range.clone()];
+            if !globals_written && section_order(*id) > section_order(GLOBAL_SECTION) {
+                let mut section = vec![];
+                write_uleb(&mut section, 1);
+                write_counter(&mut section, steps);
+                write_section(&mut out, GLOBAL_SECTION, &section);
+                globals_written = true;
+            }
+
+            let mut section = vec![];
+            match *id {
+                FUNCTION_SECTION if steps.is_some() => {
+                    let (count, entries) = split_count(content)?;
+                    write_uleb(&mut section, count + wrappers.len() as u64);
+                    section.extend(entries);
+                    for func in &wrappers {
+                        write_uleb(&mut section, info.func_types[*func as usize].into());
+                    }
+                }
+                GLOBAL_SECTION if steps.is_some() => {
+                    let (count, entries) = split_count(content)?;
+                    write_uleb(&mut section, count + 1);
+                    section.extend(entries);
+                    write_counter(&mut section, steps);
+                    globals_written = true;
+                }
+                MEMORY_SECTION if self.max_memory.is_some() => {
+                    let max_pages = self.max_memory.unwrap_or_default() / PAGE_SIZE;
+                    write_uleb(&mut section, info.memories.len() as u64);
+                    for memory in &info.memories {
+                        let maximum = memory.maximum.map_or(max_pages, |m| m.min(max_pages));
+                        if memory.initial > maximum {
+                            return Err(eco_format!(
+                                "the plugin requires at least {} KiB of memory, \
+                                 which exceeds the budget",
+                                memory.initial * PAGE_SIZE / 1024
+                            ));
+                        }
+                        write_memory(&mut section, memory, maximum);
+                    }
+                }
+                EXPORT_SECTION if steps.is_some() => write_exports(&mut section, &exports),
+                CODE_SECTION if steps.is_some() => {
+                    write_uleb(&mut section, (info.bodies.len() + wrappers.len()) as u64);
+                    for body in &info.bodies {
+                        let body = instrument_body(wasm, body, &check)?;
+                        write_uleb(&mut section, body.len() as u64);
+                        section.extend(body);
+                    }
+                    for func in &wrappers {
+                        let ty = info.func_types[*func as usize];
+                        let params = info.types[ty as usize].0;
+                        let body = wrapper_body(*func, params, counter, steps.unwrap_or_default());
+                        write_uleb(&mut section, body.len() as u64);
+                        section.extend(body);
+                    }
+                }
+                _ => section.extend(content),
+            }
+            write_section(&mut out, *id, &section);
+        }
+
+        if !globals_written {
+            let mut section = vec![];
+            write_uleb(&mut section, 1);
+            write_counter(&mut section, steps);
+            write_section(&mut out, GLOBAL_SECTION, &section);
+        }
+
+        Ok(out)
+    }
+}
+
+/// Creates a module exporting the same functions as the plugin, which return
+/// empty bytes, the only type of plugin results, without doing anything.
+fn stub_module(wasm: &[u8]) -> Result<Vec<u8>, EcoString> {
+    let info = ModuleInfo::parse(wasm)?;
+    let funcs = info
+        .exports
+        .iter()
+        .filter(|(_, kind, _)| *kind == ExternalKind::Func)
+        .map(|(name, _, index)| {
+            let ty = info.func_types.get(*index as usize).copied();
+            let params = ty.and_then(|ty| info.types.get(ty as usize)).map(|ty| ty.0);
+            (*name, params.unwrap_or_default())
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = wasm[..8].to_vec();
+
+    // (i32, ..) -> i32
+    let mut section = vec![];
+    write_uleb(&mut section, funcs.len() as u64);
+    for (_, params) in &funcs {
+        section.push(0x60);
+        write_uleb(&mut section, (*params).into());
+        section.resize(section.len() + *params as usize, 0x7f);
+        section.extend([0x01, 0x7f]);
+    }
+    write_section(&mut out, 1, &section);
+
+    let mut section = vec![];
+    write_uleb(&mut section, funcs.len() as u64);
+    for idx in 0..funcs.len() {
+        write_uleb(&mut section, idx as u64);
+    }
+    write_section(&mut out, FUNCTION_SECTION, &section);
+
+    // The host reads the result from the memory exported by the plugin.
+    write_section(&mut out, MEMORY_SECTION, &[0x01, 0x00, 0x01]);
+
+    let mut exports = funcs
+        .iter()
+        .enumerate()
+        .map(|(idx, (name, _))| (*name, ExternalKind::Func, idx as u32))
+        .collect::<Vec<_>>();
+    exports.push(("memory", ExternalKind::Memory, 0));
+    let mut section = vec![];
+    write_exports(&mut section, &exports);
+    write_section(&mut out, EXPORT_SECTION, &section);
+
+    // Returns zero, i.e. success, without sending any result.
+    let mut section = vec![];
+    write_uleb(&mut section, funcs.len() as u64);
+    for _ in &funcs {
+        section.extend([0x04, 0x00, 0x41, 0x00, 0x0b]);
+    }
+    write_section(&mut out, CODE_SECTION, &section);
+
+    Ok(out)
+}
+
+/// Finds the `plugin(..)` calls in a source that load plugins by literal
+/// paths, as the ids of the plugins and the spans of the calls.
+#[comemo::memoize]
+pub fn plugin_calls(source: &Source) -> Arc<Vec<(FileId, Span)>> {
+    let mut calls = vec![];
+    find_plugin_calls(source, source.root(), &mut calls);
+    Arc::new(calls)
+}
+
+fn find_plugin_calls(source: &Source, node: &SyntaxNode, calls: &mut Vec<(FileId, Span)>) {
+    if let Some(call) = node.cast::<ast::FuncCall>() {
+        let is_plugin = match call.callee() {
+            ast::Expr::Ident(ident) => ident.get() == "plugin",
+            ast::Expr::FieldAccess(access) => {
+                matches!(access.target(), ast::Expr::Ident(target) if target.get() == "std")
+                    && access.field().get() == "plugin"
+            }
+            _ => false,
+        };
+        let path = call.args().items().next().and_then(|arg| match arg {
+            ast::Arg::Pos(ast::Expr::Str(path)) => Some(path.get()),
+            _ => None,
+        });
+        if let Some(path) = path.filter(|_| is_plugin) {
+            calls.push((source.id().join(&path), node.span()));
+        }
+    }
+
+    for child in node.children() {
+        find_plugin_calls(source, child, calls);
+    }
+}
+
+/// The parsed parts of a module that are needed to rewrite it.
+#[derive(Default)]
+struct ModuleInfo<'a> {
+    /// The ids and content ranges of the sections, in order.
+    sections: Vec<(u8, Range<usize>)>,
+    /// The parameter and result counts of the function types.
+    types: Vec<(u32, u32)>,
+    /// The type indices of the functions, including the imported ones.
+    func_types: Vec<u32>,
+    /// The number of imported functions.
+    imported_funcs: u32,
+    /// The number of imported globals.
+    imported_globals: u32,
+    /// The number of defined globals.
+    globals: u32,
+    /// The defined memories.
+    memories: Vec<MemoryType>,
+    /// The exports, in order.
+    exports: Vec<(&'a str, ExternalKind, u32)>,
+    /// The bodies of the defined functions.
+    bodies: Vec<FunctionBody<'a>>,
+}
+
+impl<'a> ModuleInfo<'a> {
+    fn parse(wasm: &'a [u8]) -> Result<Self, EcoString> {
+        let mut info = Self::default();
+        for payload in Parser::new(0).parse_all(wasm) {
+            let payload = payload.map_err(invalid)?;
+            if let Some(section) = payload.as_section() {
+                info.sections.push(section);
+            }
+
+            match payload {
+                Payload::TypeSection(reader) => {
+                    for ty in reader {
+                        let Type::Func(ty) = ty.map_err(invalid)?;
+                        info.types
+                            .push((ty.params().len() as u32, ty.results().len() as u32));
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        match import.map_err(invalid)?.ty {
+                            TypeRef::Func(ty) => {
+                                info.func_types.push(ty);
+                                info.imported_funcs += 1;
+                            }
+                            TypeRef::Global(..) => info.imported_globals += 1,
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::FunctionSection(reader) => {
+                    for ty in reader {
+                        info.func_types.push(ty.map_err(invalid)?);
+                    }
+                }
+                Payload::GlobalSection(reader) => info.globals = reader.count(),
+                Payload::MemorySection(reader) => {
+                    for memory in reader {
+                        info.memories.push(memory.map_err(invalid)?);
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export.map_err(invalid)?;
+                        info.exports.push((export.name, export.kind, export.index));
+                    }
+                }
+                Payload::CodeSectionEntry(body) => info.bodies.push(body),
+                _ => {}
+            }
+        }
+
+        if wasm.len() < 8 {
+            return Err("invalid plugin: missing header".into());
+        }
+        Ok(info)
+    }
+}
+
+/// Inserts the step check at the entry of the function and each loop.
+fn instrument_body(wasm: &[u8], body: &FunctionBody, check: &[u8]) -> Result<Vec<u8>, EcoString> {
+    let range = body.range();
+    let mut ops = body.get_operators_reader().map_err(invalid)?;
+    let start = ops.original_position();
+
+    let mut points = vec![start];
+    let mut after_loop = false;
+    while !ops.eof() {
+        let (op, offset) = ops.read_with_offset().map_err(invalid)?;
+        if after_loop {
+            points.push(offset);
+        }
+        after_loop = matches!(op, Operator::Loop { .. });
+    }
+
+    let mut out = wasm[range.start..start].to_vec();
+    let mut cursor = start;
+    for point in points {
+        out.extend(&wasm[cursor..point]);
+        out.extend(check);
+        cursor = point;
+    }
+    out.extend(&wasm[cursor..range.end]);
+    Ok(out)
+}
+
+/// Creates the body of a function that resets the step counter and forwards
+/// the call.
+fn wrapper_body(func: u32, params: u32, counter: u32, steps: i64) -> Vec<u8> {
+    // no locals
+    let mut body = vec![0x00];
+    body.push(0x42);
+    write_sleb(&mut body, steps);
+    body.push(0x24);
+    write_uleb(&mut body, counter.into());
+    for param in 0..params {
+        body.push(0x20);
+        write_uleb(&mut body, param.into());
+    }
+    body.push(0x10);
+    write_uleb(&mut body, func.into());
+    body.push(0x0b);
+    body
+}
+
+/// Writes the global of the step counter, i.e. `(global (mut i64) (i64.const
+/// steps))`.
+fn write_counter(out: &mut Vec<u8>, steps: Option<i64>) {
+    out.extend([0x7e, 0x01, 0x42]);
+    write_sleb(out, steps.unwrap_or_default());
+    out.push(0x0b);
+}
+
+fn write_memory(out: &mut Vec<u8>, memory: &MemoryType, maximum: u64) {
+    let flags = 0x01 | (u8::from(memory.shared) << 1) | (u8::from(memory.memory64) << 2);
+    out.push(flags);
+    write_uleb(out, memory.initial);
+    write_uleb(out, maximum);
+}
+
+fn write_exports(out: &mut Vec<u8>, exports: &[(&str, ExternalKind, u32)]) {
+    write_uleb(out, exports.len() as u64);
+    for (name, kind, index) in exports {
+        write_uleb(out, name.len() as u64);
+        out.extend(name.as_bytes());
+        out.push(match kind {
+            ExternalKind::Func => 0x00,
+            ExternalKind::Table => 0x01,
+            ExternalKind::Memory => 0x02,
+            ExternalKind::Global => 0x03,
+            ExternalKind::Tag => 0x04,
+        });
+        write_uleb(out, (*index).into());
+    }
+}
+
+fn write_section(out: &mut Vec<u8>, id: u8, content: &[u8]) {
+    out.push(id);
+    write_uleb(out, content.len() as u64);
+    out.extend(content);
+}
+
+/// Splits the content of a vector section into the count and the entries.
+fn split_count(content: &[u8]) -> Result<(u64, &[u8]), EcoString> {
+    let mut count = 0;
+    for (idx, byte) in content.iter().enumerate().take(5) {
+        count |= u64::from(byte & 0x7f) << (idx * 7);
+        if byte & 0x80 == 0 {
+            return Ok((count, &content[idx + 1..]));
+        }
+    }
+
+    Err("invalid plugin: malformed section".into())
+}
+
+/// The order of the known sections in a module, or zero for custom sections.
+fn section_order(id: u8) -> u8 {
+    match id {
+        // type, import, function, table, memory
+        1..=5 => id,
+        // tag
+        13 => 6,
+        // global, export, start, element
+        6..=9 => id + 1,
+        // data count
+        12 => 11,
+        // code, data
+        10 | 11 => id + 2,
+        _ => 0,
+    }
+}
+
+fn write_uleb(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_sleb(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn invalid(err: BinaryReaderError) -> EcoString {
+    eco_format!("invalid plugin: {err}")
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::foundations::Plugin;
+    use typst::syntax::VirtualPath;
+    use wasmparser_nostd::Validator;
+
+    use super::*;
+
+    /// A plugin exporting `hello(a, b)` that loops forever, and its memory.
+    const PLUGIN: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x07, 0x01, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, // (i32, i32) -> i32
+        0x03, 0x02, 0x01, 0x00, // func 0: type 0
+        0x05, 0x03, 0x01, 0x00, 0x10, // memory: 16 pages
+        0x07, 0x12, 0x02, // exports
+        0x05, b'h', b'e', b'l', b'l', b'o', 0x00, 0x00, // hello: func 0
+        0x06, b'm', b'e', b'm', b'o', b'r', b'y', 0x02, 0x00, // memory: memory 0
+        0x0a, 0x0b, 0x01, 0x09, 0x00, // code: no locals
+        0x03, 0x40, 0x0c, 0x00, 0x0b, // loop br 0 end
+        0x41, 0x00, 0x0b, // i32.const 0 end
+    ];
+
+    fn validate(wasm: &[u8]) {
+        if let Err(err) = Validator::new().validate_all(wasm) {
+            panic!("invalid module: {err}");
+        }
+    }
+
+    fn call_hello(wasm: Vec<u8>) -> Result<Bytes, EcoString> {
+        let plugin = Plugin::new(Bytes::from(wasm))?;
+        let args = vec![Bytes::from(&b"a"[..]), Bytes::from(&b"b"[..])];
+        plugin.call("hello", args)
+    }
+
+    fn exports(wasm: &[u8]) -> Vec<(String, ExternalKind, u32)> {
+        let info = ModuleInfo::parse(wasm).unwrap();
+        let exports = info.exports.iter();
+        exports.map(|(n, k, i)| (n.to_string(), *k, *i)).collect()
+    }
+
+    #[test]
+    fn test_plugin_calls() {
+        let id = FileId::new(None, VirtualPath::new("/main.typ"));
+        let text = "#let a = plugin(\"a.wasm\")\n#let b = std.plugin(\"/lib/b.wasm\")\n\
+                    #let c = read(\"c.wasm\", encoding: none)";
+        let source = Source::new(id, text.to_owned());
+
+        let calls = plugin_calls(&source);
+        let targets = calls.iter().map(|(id, _)| id.vpath().as_rooted_path());
+        let targets = targets
+            .map(|path| path.to_string_lossy())
+            .collect::<Vec<_>>();
+        assert_eq!(targets, vec!["/a.wasm", "/lib/b.wasm"]);
+    }
+
+    #[test]
+    fn test_stub() {
+        let stub = stub_module(PLUGIN).unwrap();
+        let info = ModuleInfo::parse(&stub).unwrap();
+        assert_eq!(info.types, vec![(2, 1)]);
+        assert_eq!(
+            exports(&stub),
+            vec![
+                ("hello".to_owned(), ExternalKind::Func, 0),
+                ("memory".to_owned(), ExternalKind::Memory, 0),
+            ]
+        );
+
+        validate(&stub);
+        assert_eq!(call_hello(stub).unwrap().as_slice(), b"");
+    }
+
+    #[test]
+    fn test_budget() {
+        let budget = PluginBudget {
+            max_memory: Some(PAGE_SIZE * 32),
+            max_steps: Some(1000),
+        };
+        let wasm = budget.instrument(PLUGIN).unwrap();
+        let info = ModuleInfo::parse(&wasm).unwrap();
+        assert_eq!(info.globals, 1);
+        assert_eq!(info.bodies.len(), 2);
+        assert_eq!(info.memories[0].maximum, Some(32));
+        assert_eq!(
+            exports(&wasm)[0],
+            ("hello".to_owned(), ExternalKind::Func, 1)
+        );
+
+        validate(&wasm);
+        let err = call_hello(wasm).unwrap_err();
+        assert!(err.contains("plugin panicked"), "{err}");
+
+        // Each call has the full budget, so a plugin that fits in it keeps
+        // working across calls.
+        let once = PluginBudget {
+            max_memory: None,
+            max_steps: Some(1),
+        };
+        let wasm = once.instrument(&stub_module(PLUGIN).unwrap()).unwrap();
+        validate(&wasm);
+        let plugin = Plugin::new(Bytes::from(wasm)).unwrap();
+        for _ in 0..2 {
+            let args = vec![Bytes::from(&b"a"[..]), Bytes::from(&b"b"[..])];
+            assert_eq!(plugin.call("hello", args).unwrap().as_slice(), b"");
+        }
+
+        let small = PluginBudget {
+            max_memory: Some(PAGE_SIZE),
+            max_steps: None,
+        };
+        assert!(small.instrument(PLUGIN).is_err());
+    }
+}
@@ -81,6 +81,14 @@ impl SourceDb {
         }
     }
 
+    /// Iterates over all the files accessed during the lifecycle, whether or
+    /// not they are touched by the compilation.
+    pub fn iter_accessed_dyn(&self, f: &mut dyn FnMut(TypstFileId)) {
+        for slot in self.slots.lock().values() {
+            f(slot.fid);
+        }
+    }
+
     /// Get file content by path.
     pub fn file(&self, fid: TypstFileId, p: &impl FsProvider) -> FileResult<Bytes> {
         self.slot(fid, |slot| slot.buffer.compute(|| p.read(fid)).cloned())
@@ -14,7 +14,7 @@ use typst::{
     diag::{eco_format, At, EcoString, FileError, FileResult, SourceResult},
     foundations::{Bytes, Datetime, Dict},
     syntax::{FileId, Source, Span, VirtualPath},
-    text::{Font, FontBook},
+    text::{Font, FontBook, TextElem},
     utils::LazyHash,
     Library, World,
 };
@@ -25,11 +25,13 @@ use crate::parser::{
 };
 use crate::{
     package::{PackageRegistry, PackageSpec},
+    plugin::{plugin_calls, PluginPolicy},
     source::SourceDb,
 };
 // use crate::source::{SharedState, SourceCache, SourceDb};
 use crate::entry::{EntryManager, EntryReader, EntryState, DETACHED_ENTRY};
-use crate::{font::FontResolver, CompilerFeat, ShadowApi, WorldDeps};
+use crate::font::{FontFallback, FontResolver};
+use crate::{CompilerFeat, ShadowApi, WorldDeps};
 
 type CodespanResult<T> = Result<T, CodespanError>;
 type CodespanError = codespan_reporting::files::Error;
@@ -52,6 +54,10 @@ pub struct CompilerUniverse<F: CompilerFeat> {
     pub registry: Arc<F::Registry>,
     /// Provides path-based data access for typst compiler.
     vfs: Vfs<F::AccessModel>,
+    /// The policy to execute plugins loaded by `plugin()`.
+    pub plugin_policy: Arc<PluginPolicy>,
+    /// The fonts used when the document doesn't specify fonts.
+    pub font_fallback: Arc<FontFallback>,
 
     /// The current revision of the universe.
     pub revision: NonZeroUsize,
@@ -81,6 +87,8 @@ impl<F: CompilerFeat> CompilerUniverse<F> {
             font_resolver,
             registry,
             vfs,
+            plugin_policy: Arc::default(),
+            font_fallback: Arc::default(),
         }
     }
 
@@ -90,6 +98,18 @@ impl<F: CompilerFeat> CompilerUniverse<F> {
         self
     }
 
+    /// Wrap driver with a given plugin policy.
+    pub fn with_plugin_policy(mut self, policy: PluginPolicy) -> Self {
+        self.increment_revision(|this| this.set_plugin_policy(Arc::new(policy)));
+        self
+    }
+
+    /// Wrap driver with a given font fallback.
+    pub fn with_font_fallback(mut self, fallback: FontFallback) -> Self {
+        self.increment_revision(|this| this.set_font_fallback(Arc::new(fallback)));
+        self
+    }
+
     pub fn inputs(&self) -> Arc<LazyHash<Dict>> {
         self.inputs.clone()
     }
@@ -102,10 +122,12 @@ impl<F: CompilerFeat> CompilerUniverse<F> {
         let w = CompilerWorld {
             entry: self.entry.clone(),
             inputs: self.inputs.clone(),
-            library: create_library(self.inputs.clone()),
+            library: create_library(self.inputs.clone(), self.font_fallback.clone()),
             font_resolver: self.font_resolver.clone(),
             registry: self.registry.clone(),
             vfs: self.vfs.snapshot(),
+            plugin_policy: self.plugin_policy.clone(),
+            font_fallback: self.font_fallback.clone(),
             revision: self.revision,
             source_db: SourceDb {
                 is_compiling: true,
@@ -330,6 +352,18 @@ impl<F: CompilerFeat> RevisingUniverse<'_, F> {
         self.inner.registry = packages;
     }
 
+    /// Set the policy to execute plugins.
+    pub fn set_plugin_policy(&mut self, policy: Arc<PluginPolicy>) {
+        self.view_changed = true;
+        self.inner.plugin_policy = policy;
+    }
+
+    /// Set the fonts used when the document doesn't specify fonts.
+    pub fn set_font_fallback(&mut self, fallback: Arc<FontFallback>) {
+        self.view_changed = true;
+        self.inner.font_fallback = fallback;
+    }
+
     /// Set the inputs for the compiler.
     pub fn set_inputs(&mut self, inputs: Arc<LazyHash<Dict>>) {
         self.view_changed = true;
@@ -391,6 +425,10 @@ pub struct CompilerWorld<F: CompilerFeat> {
     pub registry: Arc<F::Registry>,
     /// Provides path-based data access for typst compiler.
     vfs: Vfs<F::AccessModel>,
+    /// The policy to execute plugins loaded by `plugin()`.
+    pub plugin_policy: Arc<PluginPolicy>,
+    /// The fonts used when the document doesn't specify fonts.
+    pub font_fallback: Arc<FontFallback>,
 
     revision: NonZeroUsize,
     /// Provides source database for typst compiler.
@@ -417,7 +455,8 @@ impl<F: CompilerFeat> CompilerWorld<F> {
         // Fetch to avoid inconsistent state.
         let _ = self.today(None);
 
-        let library = mutant.inputs.clone().map(create_library);
+        let library = (mutant.inputs.clone())
+            .map(|inputs| create_library(inputs, self.font_fallback.clone()));
 
         let root_changed = if let Some(e) = mutant.entry.as_ref() {
             self.entry.workspace_root() != e.workspace_root()
@@ -432,6 +471,8 @@ impl<F: CompilerFeat> CompilerWorld<F> {
             font_resolver: self.font_resolver.clone(),
             registry: self.registry.clone(),
             vfs: self.vfs.snapshot(),
+            plugin_policy: self.plugin_policy.clone(),
+            font_fallback: self.font_fallback.clone(),
             revision: self.revision,
             source_db: self.source_db.clone(),
             now: self.now.clone(),
@@ -492,6 +533,29 @@ impl<F: CompilerFeat> CompilerWorld<F> {
             .expect("file id does not point to any source file")
     }
 
+    /// Whether the file is loaded by a `plugin()` call in the sources that
+    /// have been accessed, which includes the calling source when typst loads
+    /// the plugin, either by compilation or by analysis. The files read by
+    /// other means, e.g. `read()`, are not.
+    fn is_plugin(&self, id: FileId) -> bool {
+        let mut sources = vec![];
+        self.source_db.iter_accessed_dyn(&mut |dep| {
+            let path = dep.vpath().as_rootless_path();
+            if path.extension().is_some_and(|ext| ext == "typ") {
+                sources.push(dep);
+            }
+        });
+
+        sources.into_iter().any(|source| {
+            let Ok(source) = self.source(source) else {
+                return false;
+            };
+            plugin_calls(&source)
+                .iter()
+                .any(|(target, _)| *target == id)
+        })
+    }
+
     fn map_source_or_default<T>(
         &self,
         id: FileId,
@@ -613,7 +677,13 @@ impl<F: CompilerFeat> World for CompilerWorld<F> {
 
     /// Try to access the specified file.
     fn file(&self, id: FileId) -> FileResult<Bytes> {
-        self.source_db.file(id, self)
+        let content = self.source_db.file(id, self)?;
+        if matches!(*self.plugin_policy, PluginPolicy::Allow) || !self.is_plugin(id) {
+            return Ok(content);
+        }
+
+        apply_plugin_policy(self.plugin_policy.clone(), content)
+            .map_err(|err| FileError::Other(Some(err)))
     }
 
     /// Get the current date.
@@ -723,11 +793,27 @@ impl<'a, F: CompilerFeat> codespan_reporting::files::Files<'a> for CompilerWorld
     }
 }
 
+/// Applies the policy to a plugin, which is cached by the content of the
+/// plugin so that it is rewritten once instead of on every read.
 #[comemo::memoize]
-fn create_library(inputs: Arc<LazyHash<Dict>>) -> Arc<LazyHash<Library>> {
-    let lib = typst::Library::builder()
+fn apply_plugin_policy(policy: Arc<PluginPolicy>, wasm: Bytes) -> Result<Bytes, EcoString> {
+    policy.apply(wasm)
+}
+
+#[comemo::memoize]
+fn create_library(
+    inputs: Arc<LazyHash<Dict>>,
+    font_fallback: Arc<FontFallback>,
+) -> Arc<LazyHash<Library>> {
+    let mut lib = typst::Library::builder()
         .with_inputs(inputs.deref().deref().clone())
         .build();
 
+    // The document still overrides the fallback by `set text(font: ..)`.
+    if !font_fallback.is_empty() {
+        lib.styles
+            .set(TextElem::set_font(font_fallback.font_list()));
+    }
+
     Arc::new(LazyHash::new(lib))
 }
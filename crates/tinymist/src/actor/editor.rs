@@ -1,7 +1,7 @@
 //! The actor maintaining output to the editor, including diagnostics and
 //! compile status.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use lsp_types::notification::{Notification, PublishDiagnostics as PublishDiagnosticsBase};
 use lsp_types::{Diagnostic, Url};
@@ -15,12 +15,27 @@ use crate::{tool::word_count::WordsCount, LspClient};
 
 /// The request to the editor actor.
 pub enum EditorRequest {
-    /// Publishes diagnostics to the editor.
-    Diag(ProjVersion, Option<DiagnosticsMap>),
+    /// Publishes diagnostics of a stage to the editor.
+    Diag(ProjVersion, DiagStage, Option<DiagnosticsMap>),
     /// Updates compile status to the editor.
     Status(CompileStatus),
     /// Updastes words count status to the editor.
     WordCount(ProjectInsId, WordsCount),
+    /// Tracks the version of a document edited by the editor.
+    DocVersion(DocVersion),
+}
+
+/// A version of a document edited by the editor, which is sent before the
+/// edit is applied to the projects.
+#[derive(Debug, Clone)]
+pub struct DocVersion {
+    /// The URL of the document.
+    pub uri: Url,
+    /// The version of the document, or `None` if the document is closed.
+    pub version: Option<i32>,
+    /// The revisions of the projects before the edit. The compilations of
+    /// later revisions contain the edit.
+    pub revisions: HashMap<ProjectInsId, usize>,
 }
 
 /// The actor maintaining output to the editor, including diagnostics and
@@ -35,13 +50,26 @@ pub struct EditorActor {
 
     /// Accumulated diagnostics per file.
     /// The outer `HashMap` is indexed by the file's URL.
-    /// The inner `HashMap` is indexed by the project ID, allowing multiple
-    /// projects publishing diagnostics to the same file independently.
-    diagnostics: HashMap<Url, HashMap<ProjectInsId, EcoVec<Diagnostic>>>,
-    /// The map from project ID to the affected files.
-    affect_map: HashMap<ProjectInsId, Vec<Url>>,
+    /// The inner `HashMap` is indexed by the diagnostics group, allowing
+    /// multiple projects and stages publishing diagnostics to the same file
+    /// independently.
+    diagnostics: HashMap<Url, HashMap<DiagGroup, EcoVec<Diagnostic>>>,
+    /// The map from diagnostics group to the affected files.
+    affect_map: HashMap<DiagGroup, Vec<Url>>,
+    /// The map from diagnostics group to the revision of the last published
+    /// diagnostics.
+    revisions: HashMap<DiagGroup, usize>,
+    /// The versions of the documents edited by the editor, which have not
+    /// been caught up by all the diagnostics groups, and the latest one that
+    /// has been.
+    doc_versions: HashMap<Url, Vec<DocVersion>>,
+    /// The document versions of the last published diagnostics per file.
+    published_versions: HashMap<Url, Option<i32>>,
 }
 
+/// A group of diagnostics published together, i.e. of a stage of a project.
+type DiagGroup = (ProjectInsId, DiagStage);
+
 impl EditorActor {
     /// Creates a new editor actor.
     pub fn new(
@@ -54,6 +82,9 @@ impl EditorActor {
             editor_rx,
             diagnostics: HashMap::new(),
             affect_map: HashMap::new(),
+            revisions: HashMap::new(),
+            doc_versions: HashMap::new(),
+            published_versions: HashMap::new(),
             notify_compile_status,
         }
     }
@@ -70,13 +101,13 @@ impl EditorActor {
 
         while let Some(req) = self.editor_rx.recv().await {
             match req {
-                EditorRequest::Diag(version, diagnostics) => {
+                EditorRequest::Diag(version, stage, diagnostics) => {
                     log::debug!(
-                        "received diagnostics from {version:?}: diag({:?})",
+                        "received {stage:?} diagnostics from {version:?}: diag({:?})",
                         diagnostics.as_ref().map(|files| files.len())
                     );
 
-                    self.publish(version.id, diagnostics).await;
+                    self.publish(version, stage, diagnostics).await;
                 }
                 EditorRequest::Status(compile_status) => {
                     log::debug!("received status request: {compile_status:?}");
@@ -93,20 +124,44 @@ impl EditorActor {
                         self.client.send_notification::<StatusAll>(&status);
                     }
                 }
+                EditorRequest::DocVersion(version) => {
+                    log::debug!("received document version: {version:?}");
+                    self.track_version(version);
+                }
             }
         }
 
         log::info!("editor actor is stopped");
     }
 
-    /// Publishes diagnostics of a project to the editor.
-    pub async fn publish(&mut self, id: ProjectInsId, next_diag: Option<DiagnosticsMap>) {
-        let affected = match next_diag.as_ref() {
-            Some(next_diag) => self
-                .affect_map
-                .insert(id.clone(), next_diag.keys().cloned().collect()),
-            None => self.affect_map.remove(&id),
+    /// Publishes diagnostics of a stage of a project to the editor. The
+    /// diagnostics older than the published ones of the same stage are
+    /// discarded.
+    pub async fn publish(
+        &mut self,
+        version: ProjVersion,
+        stage: DiagStage,
+        next_diag: Option<DiagnosticsMap>,
+    ) {
+        let id = (version.id, stage);
+        let affected = match next_diag {
+            Some(..) => {
+                let revision = self.revisions.entry(id.clone()).or_default();
+                if *revision > version.revision {
+                    log::debug!("discard outdated {stage:?} diagnostics of {version:?}");
+                    return;
+                }
+                *revision = version.revision;
+
+                let files = next_diag.iter().flat_map(|diag| diag.keys().cloned());
+                self.affect_map.insert(id.clone(), files.collect())
+            }
+            None => {
+                self.revisions.remove(&id);
+                self.affect_map.remove(&id)
+            }
         };
+        let next_diag = next_diag.map(|diag| stage.tag(diag));
 
         // Gets sources which had some diagnostic published last time, but not this
         // time.
@@ -116,49 +171,135 @@ impl EditorActor {
         // diagnostics to these sources.
 
         // Gets sources that affected by this group in last round but not this time
+        let mut published = HashSet::new();
         for uri in affected.into_iter().flatten() {
             if !next_diag.as_ref().is_some_and(|e| e.contains_key(&uri)) {
+                published.insert(uri.clone());
                 self.publish_file(&id, uri, None)
             }
         }
 
         // Gets touched updates
         for (uri, next) in next_diag.into_iter().flatten() {
+            published.insert(uri.clone());
             self.publish_file(&id, uri, Some(next))
         }
-    }
-
-    /// Publishes diagnostics of a file to the editor.
-    fn publish_file(&mut self, id: &ProjectInsId, uri: Url, next: Option<EcoVec<Diagnostic>>) {
-        let mut diagnostics = EcoVec::new();
-
-        // Gets the diagnostics from other groups
-        let path_diags = self.diagnostics.entry(uri.clone()).or_default();
-        for (existing_id, diags) in path_diags.iter() {
-            if existing_id != id {
-                diagnostics.push(diags.clone());
-            }
-        }
 
-        // Gets the diagnostics from this group
-        if let Some(diags) = &next {
-            diagnostics.push(diags.clone())
+        // Republishes the unchanged diagnostics of the documents whose versions
+        // have been caught up by all the groups, so that the editor knows they
+        // are up to date.
+        let caught_up = self.doc_versions.keys().filter(|uri| {
+            !published.contains(*uri)
+                && self
+                    .published_versions
+                    .get(*uri)
+                    .is_some_and(|published| *published != self.doc_version(uri))
+        });
+        for uri in caught_up.cloned().collect::<Vec<_>>() {
+            self.send_file(uri);
         }
+    }
 
+    /// Publishes diagnostics of a file to the editor.
+    fn publish_file(&mut self, id: &DiagGroup, uri: Url, next: Option<EcoVec<Diagnostic>>) {
         // Updates the diagnostics for this group
+        let path_diags = self.diagnostics.entry(uri.clone()).or_default();
         match next {
             Some(next) => path_diags.insert(id.clone(), next),
             None => path_diags.remove(id),
         };
 
+        self.send_file(uri);
+    }
+
+    /// Sends diagnostics of a file from all the groups to the editor.
+    fn send_file(&mut self, uri: Url) {
+        let path_diags = self.diagnostics.get(&uri);
+        let diagnostics = path_diags
+            .into_iter()
+            .flat_map(|diags| diags.values().cloned());
+        let version = self.doc_version(&uri);
+        self.published_versions.insert(uri.clone(), version);
+
         // Publishes the diagnostics
         self.client
             .send_notification::<PublishDiagnostics>(&PublishDiagnosticsParams {
                 uri,
-                diagnostics: ScatterVec(diagnostics),
-                version: None,
+                diagnostics: ScatterVec(diagnostics.collect()),
+                version,
             });
     }
+
+    /// Tracks the version of a document, dropping the versions that are no
+    /// longer needed to compute the version of the published diagnostics.
+    fn track_version(&mut self, version: DocVersion) {
+        let uri = version.uri.clone();
+        if version.version.is_none() {
+            self.doc_versions.remove(&uri);
+            self.published_versions.remove(&uri);
+            return;
+        }
+
+        let versions = self.doc_versions.entry(uri).or_default();
+        versions.push(version);
+        let caught_up = versions.iter().rposition(|version| {
+            (self.revisions.iter()).all(|((id, _), revision)| is_contained(version, id, *revision))
+        });
+        if let Some(caught_up) = caught_up {
+            versions.drain(..caught_up);
+        }
+    }
+
+    /// Gets the version of a document that the diagnostics of all the groups
+    /// are computed on, or `None` if there is not such a version.
+    fn doc_version(&self, uri: &Url) -> Option<i32> {
+        let versions = self.doc_versions.get(uri)?;
+        let group_versions = self.revisions.iter().map(|((id, _), revision)| {
+            let mut versions = versions.iter().rev();
+            let version = versions.find(|version| is_contained(version, id, *revision))?;
+            version.version
+        });
+        group_versions.min().flatten()
+    }
+}
+
+/// Checks whether the compilations of the `revision` of a project contain the
+/// edit of the document `version`.
+fn is_contained(version: &DocVersion, id: &ProjectInsId, revision: usize) -> bool {
+    // The projects created after the edit contain it.
+    (version.revisions.get(id)).is_none_or(|before| *before < revision)
+}
+
+/// The stage of the diagnostics pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagStage {
+    /// The syntax errors, which are published right after the sources are
+    /// edited.
+    Syntax,
+    /// The diagnostics of the compilation and lints, which are published when
+    /// the compilation finishes.
+    Compile,
+}
+
+impl DiagStage {
+    /// Tags the diagnostics with the stage in the `data` field, so that the
+    /// clients can render them differently, e.g. the syntax errors subtly. The
+    /// stage is merged into the existing `data` object if any.
+    fn tag(self, mut diagnostics: DiagnosticsMap) -> DiagnosticsMap {
+        let stage = serde_json::to_value(self).expect("stage is serializable");
+        for diag in diagnostics.values_mut().flat_map(|diags| diags.make_mut()) {
+            match &mut diag.data {
+                Some(serde_json::Value::Object(data)) => {
+                    data.insert("stage".to_owned(), stage.clone());
+                }
+                // Leaves the non-object data as is, which cannot carry the stage.
+                Some(..) => {}
+                None => diag.data = Some(serde_json::json!({ "stage": stage })),
+            }
+        }
+        diagnostics
+    }
 }
 
 /// The compilation revision of a project.
@@ -255,3 +396,31 @@ impl<'de> serde::Deserialize<'de> for ScatterVec<Diagnostic> {
         Ok(ScatterVec(eco_vec![vec]))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_tag_merges_data() {
+        let uri = Url::parse("file:///main.typ").unwrap();
+        let untagged = Diagnostic::default();
+        let with_data = Diagnostic {
+            data: Some(json!({ "kind": "lint" })),
+            ..Default::default()
+        };
+        let diagnostics = DiagnosticsMap::from_iter([(uri.clone(), eco_vec![untagged, with_data])]);
+
+        let tagged = DiagStage::Compile.tag(diagnostics);
+        let data = tagged[&uri].iter().map(|d| d.data.clone());
+        assert_eq!(
+            data.collect::<Vec<_>>(),
+            vec![
+                Some(json!({ "stage": "compile" })),
+                Some(json!({ "kind": "lint", "stage": "compile" })),
+            ]
+        );
+    }
+}
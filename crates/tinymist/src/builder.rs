@@ -0,0 +1,179 @@
+//! The builder to construct the language server in process.
+//!
+//! An embedding application creates a connection with any transport, e.g. an
+//! in-memory one, and builds the server on the connection:
+//!
+//! ```ignore
+//! use sync_lsp::LspClientRoot;
+//! use tinymist::ServerStateBuilder;
+//!
+//! let client = LspClientRoot::new(tokio_handle, conn.sender);
+//! ServerStateBuilder::new(client.weak())
+//!     .with_config(config)
+//!     .with_preview(false)
+//!     .with_export(false)
+//!     .build()
+//!     .start(conn.receiver, false)?;
+//! ```
+
+use std::sync::Arc;
+
+use sync_lsp::{LspBuilder, LspClient, LspDriver};
+use tinymist_std::{bail, error::Result};
+
+use crate::world::{CompileFontArgs, LspUniverse};
+use crate::{CompileConfig, Config, Derived, RegularInit, ServerState, SuperInit, UniverseFactory};
+
+/// The builder of the language server, which decouples constructing the
+/// [`ServerState`] from the command line interface.
+pub struct ServerStateBuilder {
+    /// The connection to the client.
+    client: LspClient,
+    /// The configuration to start the server with.
+    config: Config,
+    /// The font options overriding the configuration.
+    font_opts: Option<CompileFontArgs>,
+    /// The universe factory overriding the configuration.
+    universe: Option<Derived<UniverseFactory>>,
+    /// Whether to enable the preview feature, overriding the configuration.
+    preview: Option<bool>,
+    /// Whether to enable the export feature, overriding the configuration.
+    export: Option<bool>,
+}
+
+impl ServerStateBuilder {
+    /// Creates a builder with the default configuration on the client, which
+    /// can be backed by any transport.
+    pub fn new(client: LspClient) -> Self {
+        Self {
+            client,
+            config: Config::default(),
+            font_opts: None,
+            universe: None,
+            preview: None,
+            export: None,
+        }
+    }
+
+    /// Injects the configuration. The options set on this builder take
+    /// precedence over the configuration, regardless of the order of calls.
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets the font options for the compiler.
+    pub fn with_font_opts(mut self, font_opts: CompileFontArgs) -> Self {
+        self.font_opts = Some(font_opts);
+        self
+    }
+
+    /// Uses the universe created by the `factory` instead of building one from
+    /// the configuration. The factory is called again when the projects are
+    /// reloaded.
+    pub fn with_universe(
+        mut self,
+        factory: impl Fn(&CompileConfig) -> LspUniverse + Send + Sync + 'static,
+    ) -> Self {
+        self.universe = Some(Derived(Arc::new(factory)));
+        self
+    }
+
+    /// Enables or disables the preview feature.
+    pub fn with_preview(mut self, enabled: bool) -> Self {
+        self.preview = Some(enabled);
+        self
+    }
+
+    /// Enables or disables the export feature.
+    pub fn with_export(mut self, enabled: bool) -> Self {
+        self.export = Some(enabled);
+        self
+    }
+
+    /// Gets the configuration with the options set on this builder applied.
+    fn into_parts(self) -> (LspClient, Config) {
+        let mut config = self.config;
+        if let Some(font_opts) = self.font_opts {
+            config.compile.font_opts = font_opts;
+        }
+        if let Some(universe) = self.universe {
+            config.compile.universe = Some(universe);
+        }
+        if let Some(preview) = self.preview {
+            config.features.preview = preview;
+        }
+        if let Some(export) = self.export {
+            config.features.export = export;
+        }
+
+        (self.client, config)
+    }
+
+    /// Builds the language server driver, which is initialized by the
+    /// `initialize` request from the client.
+    pub fn build(self) -> LspDriver<RegularInit> {
+        let (client, config) = self.into_parts();
+        let features = config.features;
+        let init = RegularInit {
+            client: client.to_typed(),
+            config,
+            exec_cmds: Vec::new(),
+        };
+
+        ServerState::install_with(LspBuilder::new(init, client), features).build()
+    }
+
+    /// Builds the language server driver and initializes it with the injected
+    /// configuration in place, without waiting for the `initialize` request.
+    pub fn build_ready(self) -> Result<LspDriver<SuperInit>> {
+        let (client, config) = self.into_parts();
+        let features = config.features;
+        let init = SuperInit {
+            client: client.to_typed(),
+            exec_cmds: Vec::new(),
+            config,
+            err: None,
+        };
+
+        let mut driver = ServerState::install_with(LspBuilder::new(init, client), features).build();
+        match driver.ready(()) {
+            Ok(futures::future::MaybeDone::Done(Ok(..))) => Ok(driver),
+            Ok(futures::future::MaybeDone::Done(Err(err))) | Err(err) => {
+                bail!("failed to initialize the server: {err:?}")
+            }
+            Ok(..) => bail!("internal error: not sync init"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sync_lsp::transport::memory_transport;
+    use sync_lsp::LspClientRoot;
+
+    use super::*;
+
+    #[test]
+    fn test_options_override_config() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let (conn, _tx, _rx) = memory_transport();
+        let client = LspClientRoot::new(runtime.handle().clone(), conn.sender);
+
+        let font_opts = CompileFontArgs {
+            ignore_system_fonts: true,
+            ..Default::default()
+        };
+        let (_, config) = ServerStateBuilder::new(client.weak())
+            .with_preview(false)
+            .with_font_opts(font_opts)
+            .with_config(Config::default())
+            .into_parts();
+
+        assert!(!config.features.preview);
+        assert!(config.features.export);
+        assert!(config.compile.font_opts.ignore_system_fonts);
+    }
+}
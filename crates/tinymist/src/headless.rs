@@ -0,0 +1,399 @@
+//! An in-process headless client, which drives the full language server over
+//! an in-memory transport for scripted end-to-end tests.
+//!
+//! ```ignore
+//! let mut client = HeadlessClient::start("/path/to/workspace", json!({}));
+//! client.open_file("main.typ", "#let x = 1;\n#x");
+//! assert!(client.expect_diagnostics("main.typ").is_empty());
+//! let edit = client.rename("main.typ", Position::new(0, 5), "y");
+//! ```
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use lsp_server::{Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{Notification as Notif, *};
+use lsp_types::request::{Request as Req, *};
+use lsp_types::*;
+use serde_json::Value as JsonValue;
+use sync_lsp::transport::memory_transport;
+use sync_lsp::LspClientRoot;
+
+use crate::lsp_query::{
+    Batch, BatchRequest, PinSnapshot, PinSnapshotParams, ReleaseSnapshot, ReleaseSnapshotParams,
+    SnapshotQuery, SnapshotQueryParams,
+};
+use crate::ServerStateBuilder;
+
+/// The time to wait for a message from the server before panicking.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A client running the language server in process. All methods panic on
+/// failures, as it is intended to be used in tests.
+pub struct HeadlessClient {
+    /// The workspace root, to which relative paths are resolved.
+    root: PathBuf,
+    /// The sender of messages to the server.
+    tx: Sender<Message>,
+    /// The receiver of messages from the server.
+    rx: Receiver<Message>,
+    /// The thread running the server.
+    server: Option<JoinHandle<anyhow::Result<()>>>,
+    /// The id of the next request.
+    next_id: i32,
+    /// The versions of the opened documents.
+    versions: HashMap<Url, i32>,
+    /// The next version of the documents, which increases across documents
+    /// so that a reopened document does not reuse a version.
+    next_version: i32,
+    /// The last diagnostics published per file.
+    diagnostics: HashMap<Url, PublishDiagnosticsParams>,
+    /// The tokio runtime used by the server.
+    _runtime: tokio::runtime::Runtime,
+}
+
+impl HeadlessClient {
+    /// Starts a server on the workspace `root` with the initialization
+    /// options. The preview is disabled.
+    pub fn start(root: impl Into<PathBuf>, init_options: JsonValue) -> Self {
+        Self::start_with(root, init_options, |builder| builder.with_preview(false))
+    }
+
+    /// Starts a server on the workspace `root` with the initialization
+    /// options, and customizes the server by the `build` function.
+    pub fn start_with(
+        root: impl Into<PathBuf>,
+        init_options: JsonValue,
+        build: impl FnOnce(ServerStateBuilder) -> ServerStateBuilder + Send + 'static,
+    ) -> Self {
+        let root = root.into();
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("failed to create tokio runtime");
+
+        let (conn, tx, rx) = memory_transport();
+        let handle = runtime.handle().clone();
+        let server = std::thread::spawn(move || {
+            let client = LspClientRoot::new(handle, conn.sender);
+            build(ServerStateBuilder::new(client.weak()))
+                .build()
+                .start(conn.receiver, false)
+        });
+
+        let mut client = Self {
+            root,
+            tx,
+            rx,
+            server: Some(server),
+            next_id: 0,
+            versions: HashMap::new(),
+            next_version: 0,
+            diagnostics: HashMap::new(),
+            _runtime: runtime,
+        };
+
+        let root_uri = Url::from_directory_path(&client.root).expect("invalid root path");
+        #[allow(deprecated)] // `root_uri` is marked as deprecated
+        client.request::<Initialize>(InitializeParams {
+            root_uri: Some(root_uri.clone()),
+            workspace_folders: Some(vec![WorkspaceFolder {
+                uri: root_uri,
+                name: "tinymist".to_owned(),
+            }]),
+            initialization_options: Some(init_options),
+            ..Default::default()
+        });
+        client.notify::<Initialized>(InitializedParams {});
+
+        client
+    }
+
+    /// Gets the URL of a path, which is resolved against the workspace root.
+    pub fn url(&self, path: impl AsRef<Path>) -> Url {
+        Url::from_file_path(self.root.join(path)).expect("invalid file path")
+    }
+
+    /// Sends a typed request and waits for its result.
+    pub fn request<R: Req>(&mut self, params: R::Params) -> R::Result {
+        let id = RequestId::from(self.next_id);
+        self.next_id += 1;
+
+        self.send(Request::new(id.clone(), R::METHOD.to_owned(), params).into());
+        let resp = self.recv_until(R::METHOD, |msg| match msg {
+            Message::Response(resp) if resp.id == id => Some(resp.clone()),
+            _ => None,
+        });
+
+        if let Some(err) = resp.error {
+            panic!("request {} failed: {err:?}", R::METHOD);
+        }
+        let result = resp.result.unwrap_or(JsonValue::Null);
+        serde_json::from_value(result).expect("failed to deserialize response")
+    }
+
+    /// Sends a typed notification.
+    pub fn notify<N: Notif>(&mut self, params: N::Params) {
+        self.send(Notification::new(N::METHOD.to_owned(), params).into());
+    }
+
+    /// Opens a file with the content.
+    pub fn open_file(&mut self, path: impl AsRef<Path>, text: &str) {
+        let uri = self.url(path);
+        let version = self.next_version();
+        self.versions.insert(uri.clone(), version);
+        self.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri,
+                language_id: "typst".to_owned(),
+                version,
+                text: text.to_owned(),
+            },
+        });
+    }
+
+    /// Replaces the `range` of an opened file with the text.
+    pub fn edit(&mut self, path: impl AsRef<Path>, range: Range, text: &str) {
+        let uri = self.url(path);
+        assert!(self.versions.contains_key(&uri), "file is not opened");
+        let version = self.next_version();
+        self.versions.insert(uri.clone(), version);
+
+        self.notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier { uri, version },
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: Some(range),
+                range_length: None,
+                text: text.to_owned(),
+            }],
+        });
+    }
+
+    /// Saves an opened file.
+    pub fn save(&mut self, path: impl AsRef<Path>) {
+        let uri = self.url(path);
+        self.notify::<DidSaveTextDocument>(DidSaveTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri },
+            text: None,
+        });
+    }
+
+    /// Closes an opened file.
+    pub fn close_file(&mut self, path: impl AsRef<Path>) {
+        let uri = self.url(path);
+        self.versions.remove(&uri);
+        self.notify::<DidCloseTextDocument>(DidCloseTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri },
+        });
+    }
+
+    /// Requests completion at the position.
+    pub fn completion_at(
+        &mut self,
+        path: impl AsRef<Path>,
+        position: Position,
+    ) -> Option<CompletionResponse> {
+        let text_document_position = self.position(path, position);
+        self.request::<Completion>(CompletionParams {
+            text_document_position,
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: None,
+        })
+    }
+
+    /// Requests hover at the position.
+    pub fn hover_at(&mut self, path: impl AsRef<Path>, position: Position) -> Option<Hover> {
+        let text_document_position_params = self.position(path, position);
+        self.request::<HoverRequest>(HoverParams {
+            text_document_position_params,
+            work_done_progress_params: Default::default(),
+        })
+    }
+
+    /// Requests renaming the symbol at the position.
+    pub fn rename(
+        &mut self,
+        path: impl AsRef<Path>,
+        position: Position,
+        new_name: &str,
+    ) -> Option<WorkspaceEdit> {
+        let text_document_position = self.position(path, position);
+        self.request::<Rename>(RenameParams {
+            text_document_position,
+            new_name: new_name.to_owned(),
+            work_done_progress_params: Default::default(),
+        })
+    }
+
+    /// Sends the requests in one `tinymist/batch` request and returns the
+    /// responses, each of which has either a `result` or an `error`.
+    pub fn batch(&mut self, requests: Vec<(&str, JsonValue)>) -> Vec<JsonValue> {
+        let requests = requests
+            .into_iter()
+            .map(|(method, params)| BatchRequest {
+                method: method.to_owned(),
+                params,
+            })
+            .collect();
+        let responses = self.request::<Batch>(requests);
+        let responses = responses.into_iter().map(serde_json::to_value);
+        responses
+            .collect::<Result<_, _>>()
+            .expect("failed to serialize response")
+    }
+
+    /// Pins a snapshot resolved by the file and returns its token.
+    pub fn pin_snapshot(&mut self, path: impl AsRef<Path>) -> String {
+        let uri = self.url(path);
+        let params = PinSnapshotParams {
+            text_document: Some(TextDocumentIdentifier { uri }),
+        };
+        self.request::<PinSnapshot>(params).token
+    }
+
+    /// Sends a request to run on the pinned snapshot and returns its result.
+    pub fn snapshot_query(&mut self, token: &str, method: &str, params: JsonValue) -> JsonValue {
+        self.request::<SnapshotQuery>(SnapshotQueryParams {
+            token: token.to_owned(),
+            request: BatchRequest {
+                method: method.to_owned(),
+                params,
+            },
+        })
+    }
+
+    /// Releases a pinned snapshot.
+    pub fn release_snapshot(&mut self, token: &str) {
+        let token = token.to_owned();
+        self.request::<ReleaseSnapshot>(ReleaseSnapshotParams { token });
+    }
+
+    /// Executes a command of the server.
+    pub fn execute_command(&mut self, command: &str, arguments: Vec<JsonValue>) -> JsonValue {
+        self.request::<ExecuteCommand>(ExecuteCommandParams {
+            command: command.to_owned(),
+            arguments,
+            work_done_progress_params: Default::default(),
+        })
+        .unwrap_or(JsonValue::Null)
+    }
+
+    /// Waits for the diagnostics of the file published on its current version
+    /// and returns them. The diagnostics of a file that is not opened are
+    /// published without a version.
+    pub fn expect_diagnostics(&mut self, path: impl AsRef<Path>) -> Vec<Diagnostic> {
+        self.wait_diagnostics(path, |_| true)
+    }
+
+    /// Waits until the diagnostics of the file published on its current
+    /// version satisfy `f` and returns them.
+    pub fn wait_diagnostics(
+        &mut self,
+        path: impl AsRef<Path>,
+        f: impl Fn(&[Diagnostic]) -> bool,
+    ) -> Vec<Diagnostic> {
+        let uri = self.url(path);
+        let version = self.versions.get(&uri).copied();
+        let accepts = |params: &PublishDiagnosticsParams| {
+            params.uri == uri && params.version == version && f(&params.diagnostics)
+        };
+        if let Some(params) = self.diagnostics.get(&uri).filter(|params| accepts(params)) {
+            return params.diagnostics.clone();
+        }
+
+        let method = PublishDiagnostics::METHOD;
+        self.recv_until(method, |msg| match msg {
+            Message::Notification(notif) if notif.method == method => {
+                let params: PublishDiagnosticsParams =
+                    serde_json::from_value(notif.params.clone()).ok()?;
+                accepts(&params).then_some(params.diagnostics)
+            }
+            _ => None,
+        })
+    }
+
+    /// Shuts down the server and waits for it to exit.
+    pub fn shutdown(mut self) {
+        self.request::<Shutdown>(());
+        self.stop();
+    }
+
+    fn position(&self, path: impl AsRef<Path>, position: Position) -> TextDocumentPositionParams {
+        TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {
+                uri: self.url(path),
+            },
+            position,
+        }
+    }
+
+    fn send(&self, msg: Message) {
+        self.tx.send(msg).expect("server is stopped");
+    }
+
+    fn next_version(&mut self) -> i32 {
+        self.next_version += 1;
+        self.next_version
+    }
+
+    /// Receives messages until `f` accepts one, while handling the others.
+    fn recv_until<T>(&mut self, waiting: &str, f: impl Fn(&Message) -> Option<T>) -> T {
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            let msg = match self.rx.recv_deadline(deadline) {
+                Ok(msg) => msg,
+                Err(RecvTimeoutError::Timeout) => panic!("timeout waiting for {waiting}"),
+                Err(RecvTimeoutError::Disconnected) => panic!("server exited waiting {waiting}"),
+            };
+
+            let res = f(&msg);
+            self.handle(msg);
+            if let Some(res) = res {
+                return res;
+            }
+        }
+    }
+
+    fn handle(&mut self, msg: Message) {
+        match msg {
+            Message::Notification(notif) if notif.method == PublishDiagnostics::METHOD => {
+                let Ok(params) = serde_json::from_value::<PublishDiagnosticsParams>(notif.params)
+                else {
+                    return;
+                };
+                self.diagnostics.insert(params.uri.clone(), params);
+            }
+            // Accepts all requests from the server, e.g. `client/registerCapability`.
+            Message::Request(req) => {
+                self.send(Response::new_ok(req.id, JsonValue::Null).into());
+            }
+            Message::Notification(..) | Message::Response(..) => {}
+        }
+    }
+
+    fn stop(&mut self) {
+        let Some(server) = self.server.take() else {
+            return;
+        };
+
+        let _ = self
+            .tx
+            .send(Notification::new(Exit::METHOD.to_owned(), ()).into());
+        match server.join() {
+            Ok(res) => res.expect("server exited with error"),
+            Err(err) => std::panic::resume_unwind(err),
+        }
+    }
+}
+
+impl Drop for HeadlessClient {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            self.stop();
+        }
+    }
+}
@@ -27,6 +27,8 @@ use typst_shim::utils::{Deferred, LazyHash};
 // textDocument.definition.linkSupport capability.
 
 use super::*;
+use crate::world::font::FontFallback;
+use crate::world::plugin::{PluginBudget, PluginPolicy};
 use crate::world::ImmutDict;
 
 /// Capability to add valid commands to the arguments.
@@ -39,8 +41,9 @@ pub trait AddCommands {
 pub struct RegularInit {
     /// The connection to the client.
     pub client: TypedLspClient<ServerState>,
-    /// The font options for the compiler.
-    pub font_opts: CompileFontArgs,
+    /// The base configuration, which is updated by the initialization
+    /// parameters.
+    pub config: Config,
     /// The commands to execute.
     pub exec_cmds: Vec<String>,
 }
@@ -69,7 +72,7 @@ impl Initializer for RegularInit {
     ///
     /// # Errors
     /// Errors if the configuration could not be updated.
-    fn initialize(mut self, params: InitializeParams) -> (ServerState, AnySchedulableResponse) {
+    fn initialize(self, params: InitializeParams) -> (ServerState, AnySchedulableResponse) {
         // Initialize configurations
         let roots = match params.workspace_folders.as_ref() {
             Some(roots) => roots
@@ -85,18 +88,11 @@ impl Initializer for RegularInit {
                 .into_iter()
                 .collect(),
         };
-        let mut config = Config {
-            const_config: ConstConfig::from(&params),
-            compile: CompileConfig {
-                entry_resolver: EntryResolver {
-                    roots,
-                    ..Default::default()
-                },
-                font_opts: std::mem::take(&mut self.font_opts),
-                ..CompileConfig::default()
-            },
-            ..Config::default()
-        };
+        let mut config = self.config;
+        config.const_config = ConstConfig::from(&params);
+        if !roots.is_empty() {
+            config.compile.entry_resolver.roots = roots;
+        }
         let err = params.initialization_options.and_then(|init| {
             config
                 .update(&init)
@@ -281,10 +277,12 @@ const CONFIG_ITEMS: &[&str] = &[
     "completion",
     "fontPaths",
     "systemFonts",
+    "fontFallback",
     "typstExtraArgs",
     "compileStatus",
     "colorTheme",
     "hoverPeriscope",
+    "plugin",
 ];
 // endregion Configuration Items
 
@@ -309,6 +307,9 @@ pub struct Config {
     pub support_html_in_markdown: bool,
     /// Tinymist's completion features.
     pub completion: CompletionFeat,
+    /// The server features, which are set by the embedder rather than the
+    /// editor.
+    pub features: ServerFeatures,
 }
 
 impl Config {
@@ -426,7 +427,11 @@ impl Config {
             task: ProjectTask::ExportPdf(ExportPdfTask {
                 export: ExportTask {
                     output: Some(compile_config.output_path.clone()),
-                    when: compile_config.export_pdf,
+                    when: if self.features.export {
+                        compile_config.export_pdf
+                    } else {
+                        TaskWhen::Never
+                    },
                     transform: vec![],
                 },
                 pdf_standards: vec![],
@@ -437,6 +442,27 @@ impl Config {
     }
 }
 
+/// The features of the server that can be turned off when embedding it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerFeatures {
+    /// Whether to serve the preview commands.
+    pub preview: bool,
+    /// Whether to export documents, either on demand or on save/type.
+    pub export: bool,
+}
+
+impl Default for ServerFeatures {
+    fn default() -> Self {
+        Self {
+            preview: true,
+            export: true,
+        }
+    }
+}
+
+/// Creates the universe of a project from the compile configuration.
+pub type UniverseFactory = Arc<dyn Fn(&CompileConfig) -> LspUniverse + Send + Sync>;
+
 /// Configuration set at initialization that won't change within a single
 /// session.
 #[derive(Debug, Clone)]
@@ -515,6 +541,8 @@ pub struct CompileConfig {
     pub system_fonts: Option<bool>,
     /// Specifies the font paths
     pub font_paths: Vec<PathBuf>,
+    /// The fonts used when the document doesn't specify fonts.
+    pub font_fallback: FontFallback,
     /// Computed fonts based on configuration.
     pub fonts: OnceCell<Derived<Deferred<Arc<TinymistFontResolver>>>>,
     /// Notify the compile status to the editor.
@@ -531,6 +559,10 @@ pub struct CompileConfig {
     pub lsp_inputs: ImmutDict,
     /// The entry resolver.
     pub entry_resolver: EntryResolver,
+    /// Creates the universe in place of building it from the configuration.
+    pub universe: Option<Derived<UniverseFactory>>,
+    /// The configuration to execute plugins.
+    pub plugin: PluginConfig,
 }
 
 impl CompileConfig {
@@ -608,8 +640,15 @@ impl CompileConfig {
             });
         }
 
+        self.plugin = deser_or_default!("plugin", PluginConfig);
         self.font_paths = try_or_default(|| Vec::<_>::deserialize(update.get("fontPaths")?).ok());
         self.system_fonts = try_(|| update.get("systemFonts")?.as_bool());
+        self.font_fallback = deser_or_default!("fontFallback", FontFallback);
+        if self.font_fallback.is_empty() {
+            let extra_args = self.typst_extra_args.as_ref();
+            self.font_fallback =
+                extra_args.map_or_else(Default::default, |x| x.font.fallback.clone());
+        }
 
         self.entry_resolver.project_resolution = project_resolution;
         self.entry_resolver.root_path =
@@ -767,6 +806,48 @@ impl CompileConfig {
     }
 }
 
+/// The configuration to execute WebAssembly plugins loaded by `plugin()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PluginConfig {
+    /// Whether the workspace is trusted by the user. Plugins are replaced by
+    /// stubs in untrusted workspaces.
+    pub trusted: bool,
+    /// The maximum memory in MiB that a plugin can use. No limit by default.
+    pub max_memory: Option<u64>,
+    /// The maximum loop iterations and function calls that a call to a plugin
+    /// can take, which bounds its execution time. No limit by default.
+    pub max_steps: Option<u64>,
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            trusted: true,
+            max_memory: None,
+            max_steps: None,
+        }
+    }
+}
+
+impl PluginConfig {
+    /// Determines the policy to execute plugins.
+    pub fn policy(&self) -> PluginPolicy {
+        if !self.trusted {
+            return PluginPolicy::Stub;
+        }
+        // The plugins are left untouched unless a budget is configured.
+        if self.max_memory.is_none() && self.max_steps.is_none() {
+            return PluginPolicy::Allow;
+        }
+
+        PluginPolicy::Budget(PluginBudget {
+            max_memory: self.max_memory.map(|mib| mib.saturating_mul(1024 * 1024)),
+            max_steps: self.max_steps,
+        })
+    }
+}
+
 /// The mode of the formatter.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -895,6 +976,59 @@ mod tests {
         assert_eq!(config.compile.export_pdf, TaskWhen::OnType);
     }
 
+    #[test]
+    fn test_plugin_config() {
+        let mut config = Config::default();
+
+        config.update(&json!({})).unwrap();
+        assert_eq!(config.compile.plugin.policy(), PluginPolicy::Allow);
+
+        let update = json!({
+            "plugin": { "trusted": false }
+        });
+        config.update(&update).unwrap();
+        assert_eq!(config.compile.plugin.policy(), PluginPolicy::Stub);
+
+        let update = json!({
+            "plugin": { "maxMemory": 1, "maxSteps": null }
+        });
+        config.update(&update).unwrap();
+        assert_eq!(
+            config.compile.plugin.policy(),
+            PluginPolicy::Budget(PluginBudget {
+                max_memory: Some(1024 * 1024),
+                max_steps: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_font_fallback_config() {
+        let mut config = Config::default();
+
+        config.update(&json!({})).unwrap();
+        assert!(config.compile.font_fallback.is_empty());
+
+        let update = json!({
+            "fontFallback": { "latin": ["Inria Serif"], "cjk": ["Noto Serif CJK SC"] }
+        });
+        config.update(&update).unwrap();
+        assert_eq!(
+            config.compile.font_fallback,
+            FontFallback {
+                latin: vec!["Inria Serif".into()],
+                cjk: vec!["Noto Serif CJK SC".into()],
+                emoji: vec![],
+            }
+        );
+
+        let update = json!({
+            "typstExtraArgs": ["--font-fallback-emoji", "Noto Color Emoji"]
+        });
+        config.update(&update).unwrap();
+        assert_eq!(config.compile.font_fallback.emoji, ["Noto Color Emoji"]);
+    }
+
     #[test]
     fn test_config_creation_timestamp() {
         type Timestamp = Option<i64>;
@@ -1051,6 +1185,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_export_disabled_by_features() {
+        let when = |config: &Config| config.export().task.as_export().unwrap().when;
+
+        let mut config = Config::default();
+        config
+            .update(&json!({ "exportPdf": "onSave" }))
+            .expect("updated");
+        assert_eq!(when(&config), TaskWhen::OnSave);
+
+        config.features.export = false;
+        assert_eq!(when(&config), TaskWhen::Never);
+    }
+
     #[test]
     fn test_default_formatting_config() {
         let config = Config::default().formatter();
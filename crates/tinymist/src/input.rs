@@ -8,6 +8,7 @@ use tinymist_std::error::prelude::*;
 use tinymist_std::ImmutPath;
 use typst::{diag::FileResult, syntax::Source};
 
+use crate::actor::editor::{DocVersion, EditorRequest};
 use crate::route::ProjectResolution;
 use crate::world::vfs::{notify::MemoryEvent, FileChangeSet};
 use crate::world::TaskInputs;
@@ -87,6 +88,23 @@ impl ServerState {
         self.update_sources(files)
     }
 
+    /// Tracks the version of a document edited by the client, or closed if
+    /// the version is `None`. It must be called before the edit is applied,
+    /// so that the diagnostics published afterwards carry the version.
+    pub(crate) fn track_version(&mut self, uri: Url, version: Option<i32>) {
+        let projects = self.project.compiler.projects();
+        let revisions = projects.map(|proj| (proj.id.clone(), proj.verse.revision.get()));
+        let version = DocVersion {
+            uri,
+            version,
+            revisions: revisions.collect(),
+        };
+
+        self.editor_tx
+            .send(EditorRequest::DocVersion(version))
+            .log_error("failed to send document version");
+    }
+
     /// Queries a source file that must be in memory.
     pub fn query_source<T>(
         &self,
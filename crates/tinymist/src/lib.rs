@@ -19,7 +19,10 @@
 //! See [CONTRIBUTING.md](https://github.com/Myriad-Dreamin/tinymist/blob/main/CONTRIBUTING.md).
 
 mod actor;
+mod builder;
 mod cmd;
+#[cfg(feature = "headless")]
+pub mod headless;
 mod init;
 pub(crate) mod input;
 pub(crate) mod lsp;
@@ -33,6 +36,7 @@ mod task;
 pub mod tool;
 mod utils;
 
+pub use builder::*;
 pub use init::*;
 pub use server::*;
 pub use sync_lsp::LspClient;
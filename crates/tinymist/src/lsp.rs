@@ -80,8 +80,10 @@ impl ServerState {
 impl ServerState {
     pub(crate) fn did_open(&mut self, params: DidOpenTextDocumentParams) -> LspResult<()> {
         log::info!("did open {:?}", params.text_document.uri);
-        let path = as_path_(params.text_document.uri);
-        let text = params.text_document.text;
+        let doc = params.text_document;
+        self.track_version(doc.uri.clone(), Some(doc.version));
+        let path = as_path_(doc.uri);
+        let text = doc.text;
 
         self.create_source(path.clone(), text)
             .map_err(|e| invalid_params(e.to_string()))?;
@@ -92,6 +94,7 @@ impl ServerState {
     }
 
     pub(crate) fn did_close(&mut self, params: DidCloseTextDocumentParams) -> LspResult<()> {
+        self.track_version(params.text_document.uri.clone(), None);
         let path = as_path_(params.text_document.uri);
 
         self.remove_source(path.clone())
@@ -100,7 +103,9 @@ impl ServerState {
     }
 
     pub(crate) fn did_change(&mut self, params: DidChangeTextDocumentParams) -> LspResult<()> {
-        let path = as_path_(params.text_document.uri);
+        let doc = params.text_document;
+        self.track_version(doc.uri.clone(), Some(doc.version));
+        let path = as_path_(doc.uri);
         let changes = params.content_changes;
 
         self.edit_source(path.clone(), changes, self.const_config().position_encoding)
@@ -136,8 +141,14 @@ impl ServerState {
             self.change_export_config(new_export_config);
         }
 
-        if old_config.compile.primary_opts() != self.config.compile.primary_opts() {
+        let primary_changed =
+            old_config.compile.primary_opts() != self.config.compile.primary_opts();
+        if primary_changed {
             self.config.compile.fonts = OnceCell::new(); // todo: don't reload fonts if not changed
+        }
+        let world_changed = old_config.compile.plugin != self.config.compile.plugin
+            || old_config.compile.font_fallback != self.config.compile.font_fallback;
+        if primary_changed || world_changed {
             self.reload_projects()
                 .log_error("could not restart primary");
         }
@@ -1,17 +1,24 @@
 //! tinymist's language server
 
+use std::path::Path;
+
 use futures::future::MaybeDone;
 use lsp_server::RequestId;
 use lsp_types::request::GotoDeclarationParams;
 use lsp_types::*;
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 use sync_lsp::*;
 use tinymist_project::{EntryState, TaskInputs, DETACHED_ENTRY};
-use tinymist_query::SyntaxRequest;
+use tinymist_query::{
+    LocalContext, LspWorldExt, PositionEncoding, SemanticRequest, StatefulRequest, SyntaxRequest,
+    VersionedDocument,
+};
 use tinymist_std::{ImmutPath, Result};
+use typst::syntax::Source;
 
 use super::ServerState;
-use crate::{as_path, as_path_, as_path_pos, FormatterMode};
+use crate::{as_path, as_path_, FormatterMode};
 
 use tinymist_query::{CompilerQueryRequest, CompilerQueryResponse, FoldRequestFeature};
 
@@ -57,8 +64,7 @@ impl ServerState {
         req_id: RequestId,
         params: GotoDefinitionParams,
     ) -> ScheduledResult {
-        let (path, position) = as_path_pos(params.text_document_position_params);
-        run_query!(req_id, self.GotoDefinition(path, position))
+        self.schedule_query(req_id, to_query::goto_definition(params))
     }
 
     pub(crate) fn goto_declaration(
@@ -66,8 +72,7 @@ impl ServerState {
         req_id: RequestId,
         params: GotoDeclarationParams,
     ) -> ScheduledResult {
-        let (path, position) = as_path_pos(params.text_document_position_params);
-        run_query!(req_id, self.GotoDeclaration(path, position))
+        self.schedule_query(req_id, to_query::goto_declaration(params))
     }
 
     pub(crate) fn references(
@@ -75,14 +80,13 @@ impl ServerState {
         req_id: RequestId,
         params: ReferenceParams,
     ) -> ScheduledResult {
-        let (path, position) = as_path_pos(params.text_document_position);
-        run_query!(req_id, self.References(path, position))
+        self.schedule_query(req_id, to_query::references(params))
     }
 
     pub(crate) fn hover(&mut self, req_id: RequestId, params: HoverParams) -> ScheduledResult {
-        let (path, position) = as_path_pos(params.text_document_position_params);
-        self.implicit_focus_entry(|| Some(path.as_path().into()), 'h');
-        run_query!(req_id, self.Hover(path, position))
+        let query = to_query::hover(params);
+        self.implicit_focus_entry(|| query.associated_path().map(From::from), 'h');
+        self.schedule_query(req_id, query)
     }
 
     pub(crate) fn folding_range(
@@ -90,10 +94,10 @@ impl ServerState {
         req_id: RequestId,
         params: FoldingRangeParams,
     ) -> ScheduledResult {
-        let path = as_path(params.text_document);
         let line_folding_only = self.const_config().doc_line_folding_only;
-        self.implicit_focus_entry(|| Some(path.as_path().into()), 'f');
-        run_query!(req_id, self.FoldingRange(path, line_folding_only))
+        let query = to_query::folding_range(params, line_folding_only);
+        self.implicit_focus_entry(|| query.associated_path().map(From::from), 'f');
+        self.schedule_query(req_id, query)
     }
 
     pub(crate) fn selection_range(
@@ -101,9 +105,7 @@ impl ServerState {
         req_id: RequestId,
         params: SelectionRangeParams,
     ) -> ScheduledResult {
-        let path = as_path(params.text_document);
-        let positions = params.positions;
-        run_query!(req_id, self.SelectionRange(path, positions))
+        self.schedule_query(req_id, to_query::selection_range(params))
     }
 
     pub(crate) fn document_highlight(
@@ -111,8 +113,7 @@ impl ServerState {
         req_id: RequestId,
         params: DocumentHighlightParams,
     ) -> ScheduledResult {
-        let (path, position) = as_path_pos(params.text_document_position_params);
-        run_query!(req_id, self.DocumentHighlight(path, position))
+        self.schedule_query(req_id, to_query::document_highlight(params))
     }
 
     pub(crate) fn document_symbol(
@@ -120,8 +121,7 @@ impl ServerState {
         req_id: RequestId,
         params: DocumentSymbolParams,
     ) -> ScheduledResult {
-        let path = as_path(params.text_document);
-        run_query!(req_id, self.DocumentSymbol(path))
+        self.schedule_query(req_id, to_query::document_symbol(params))
     }
 
     pub(crate) fn semantic_tokens_full(
@@ -166,9 +166,7 @@ impl ServerState {
         req_id: RequestId,
         params: InlayHintParams,
     ) -> ScheduledResult {
-        let path = as_path(params.text_document);
-        let range = params.range;
-        run_query!(req_id, self.InlayHint(path, range))
+        self.schedule_query(req_id, to_query::inlay_hint(params))
     }
 
     pub(crate) fn document_color(
@@ -176,8 +174,7 @@ impl ServerState {
         req_id: RequestId,
         params: DocumentColorParams,
     ) -> ScheduledResult {
-        let path = as_path(params.text_document);
-        run_query!(req_id, self.DocumentColor(path))
+        self.schedule_query(req_id, to_query::document_color(params))
     }
 
     pub(crate) fn document_link(
@@ -185,8 +182,7 @@ impl ServerState {
         req_id: RequestId,
         params: DocumentLinkParams,
     ) -> ScheduledResult {
-        let path = as_path(params.text_document);
-        run_query!(req_id, self.DocumentLink(path))
+        self.schedule_query(req_id, to_query::document_link(params))
     }
 
     pub(crate) fn color_presentation(
@@ -194,10 +190,7 @@ impl ServerState {
         req_id: RequestId,
         params: ColorPresentationParams,
     ) -> ScheduledResult {
-        let path = as_path(params.text_document);
-        let color = params.color;
-        let range = params.range;
-        run_query!(req_id, self.ColorPresentation(path, color, range))
+        self.schedule_query(req_id, to_query::color_presentation(params))
     }
 
     pub(crate) fn code_action(
@@ -205,9 +198,7 @@ impl ServerState {
         req_id: RequestId,
         params: CodeActionParams,
     ) -> ScheduledResult {
-        let path = as_path(params.text_document);
-        let range = params.range;
-        run_query!(req_id, self.CodeAction(path, range))
+        self.schedule_query(req_id, to_query::code_action(params))
     }
 
     pub(crate) fn code_lens(
@@ -215,8 +206,7 @@ impl ServerState {
         req_id: RequestId,
         params: CodeLensParams,
     ) -> ScheduledResult {
-        let path = as_path(params.text_document);
-        run_query!(req_id, self.CodeLens(path))
+        self.schedule_query(req_id, to_query::code_lens(params))
     }
 
     pub(crate) fn completion(
@@ -224,19 +214,7 @@ impl ServerState {
         req_id: RequestId,
         params: CompletionParams,
     ) -> ScheduledResult {
-        let (path, position) = as_path_pos(params.text_document_position);
-        let context = params.context.as_ref();
-        let explicit =
-            context.is_some_and(|context| context.trigger_kind == CompletionTriggerKind::INVOKED);
-        let trigger_character = params
-            .context
-            .and_then(|c| c.trigger_character)
-            .and_then(|c| c.chars().next());
-
-        run_query!(
-            req_id,
-            self.Completion(path, position, explicit, trigger_character)
-        )
+        self.schedule_query(req_id, to_query::completion(params))
     }
 
     pub(crate) fn signature_help(
@@ -244,14 +222,11 @@ impl ServerState {
         req_id: RequestId,
         params: SignatureHelpParams,
     ) -> ScheduledResult {
-        let (path, position) = as_path_pos(params.text_document_position_params);
-        run_query!(req_id, self.SignatureHelp(path, position))
+        self.schedule_query(req_id, to_query::signature_help(params))
     }
 
     pub(crate) fn rename(&mut self, req_id: RequestId, params: RenameParams) -> ScheduledResult {
-        let (path, position) = as_path_pos(params.text_document_position);
-        let new_name = params.new_name;
-        run_query!(req_id, self.Rename(path, position, new_name))
+        self.schedule_query(req_id, to_query::rename(params))
     }
 
     pub(crate) fn prepare_rename(
@@ -259,8 +234,7 @@ impl ServerState {
         req_id: RequestId,
         params: TextDocumentPositionParams,
     ) -> ScheduledResult {
-        let (path, position) = as_path_pos(params);
-        run_query!(req_id, self.PrepareRename(path, position))
+        self.schedule_query(req_id, to_query::prepare_rename(params))
     }
 
     pub(crate) fn symbol(
@@ -268,14 +242,11 @@ impl ServerState {
         req_id: RequestId,
         params: WorkspaceSymbolParams,
     ) -> ScheduledResult {
-        let pattern = (!params.query.is_empty()).then_some(params.query);
-        run_query!(req_id, self.Symbol(pattern))
+        self.schedule_query(req_id, to_query::symbol(params))
     }
 
     pub(crate) fn on_enter(&mut self, req_id: RequestId, params: OnEnterParams) -> ScheduledResult {
-        let path = as_path(params.text_document);
-        let range = params.range;
-        run_query!(req_id, self.OnEnter(path, range))
+        self.schedule_query(req_id, to_query::on_enter(params))
     }
 
     pub(crate) fn will_rename_files(
@@ -298,17 +269,161 @@ impl ServerState {
 
         run_query!(req_id, self.WillRenameFiles(paths))
     }
+
+    /// Schedules a query converted from the parameters of a request.
+    fn schedule_query(
+        &mut self,
+        req_id: RequestId,
+        query: CompilerQueryRequest,
+    ) -> ScheduledResult {
+        let query_fut = self.query(query);
+        self.client.untyped().schedule_query(req_id, query_fut)
+    }
 }
 
-macro_rules! query_source {
-    ($self:ident, $method:ident, $req:expr) => {{
-        let path: ImmutPath = $req.path.clone().into();
+/// Converts the parameters of the LSP requests to queries, which is shared by
+/// the request handlers and the batch requests.
+mod to_query {
+    use lsp_types::*;
+    use tinymist_query::*;
+
+    use super::{GotoDeclarationParams, OnEnterParams};
+    use crate::{as_path, as_path_pos};
 
-        $self.query_source(path, |source| {
-            let enc = $self.const_config().position_encoding;
-            let res = $req.request(&source, enc);
-            Ok(CompilerQueryResponse::$method(res))
+    type Q = CompilerQueryRequest;
+
+    pub fn goto_definition(params: GotoDefinitionParams) -> Q {
+        let (path, position) = as_path_pos(params.text_document_position_params);
+        Q::GotoDefinition(GotoDefinitionRequest { path, position })
+    }
+
+    pub fn goto_declaration(params: GotoDeclarationParams) -> Q {
+        let (path, position) = as_path_pos(params.text_document_position_params);
+        Q::GotoDeclaration(GotoDeclarationRequest { path, position })
+    }
+
+    pub fn references(params: ReferenceParams) -> Q {
+        let (path, position) = as_path_pos(params.text_document_position);
+        Q::References(ReferencesRequest { path, position })
+    }
+
+    pub fn hover(params: HoverParams) -> Q {
+        let (path, position) = as_path_pos(params.text_document_position_params);
+        Q::Hover(HoverRequest { path, position })
+    }
+
+    pub fn folding_range(params: FoldingRangeParams, line_folding_only: bool) -> Q {
+        let path = as_path(params.text_document);
+        Q::FoldingRange(FoldingRangeRequest {
+            path,
+            line_folding_only,
         })
+    }
+
+    pub fn selection_range(params: SelectionRangeParams) -> Q {
+        let path = as_path(params.text_document);
+        let positions = params.positions;
+        Q::SelectionRange(SelectionRangeRequest { path, positions })
+    }
+
+    pub fn document_highlight(params: DocumentHighlightParams) -> Q {
+        let (path, position) = as_path_pos(params.text_document_position_params);
+        Q::DocumentHighlight(DocumentHighlightRequest { path, position })
+    }
+
+    pub fn document_symbol(params: DocumentSymbolParams) -> Q {
+        let path = as_path(params.text_document);
+        Q::DocumentSymbol(DocumentSymbolRequest { path })
+    }
+
+    pub fn inlay_hint(params: InlayHintParams) -> Q {
+        let path = as_path(params.text_document);
+        let range = params.range;
+        Q::InlayHint(InlayHintRequest { path, range })
+    }
+
+    pub fn document_color(params: DocumentColorParams) -> Q {
+        let path = as_path(params.text_document);
+        Q::DocumentColor(DocumentColorRequest { path })
+    }
+
+    pub fn document_link(params: DocumentLinkParams) -> Q {
+        let path = as_path(params.text_document);
+        Q::DocumentLink(DocumentLinkRequest { path })
+    }
+
+    pub fn color_presentation(params: ColorPresentationParams) -> Q {
+        let path = as_path(params.text_document);
+        let color = params.color;
+        let range = params.range;
+        Q::ColorPresentation(ColorPresentationRequest { path, color, range })
+    }
+
+    pub fn code_action(params: CodeActionParams) -> Q {
+        let path = as_path(params.text_document);
+        let range = params.range;
+        Q::CodeAction(CodeActionRequest { path, range })
+    }
+
+    pub fn code_lens(params: CodeLensParams) -> Q {
+        let path = as_path(params.text_document);
+        Q::CodeLens(CodeLensRequest { path })
+    }
+
+    pub fn completion(params: CompletionParams) -> Q {
+        let (path, position) = as_path_pos(params.text_document_position);
+        let context = params.context.as_ref();
+        let explicit =
+            context.is_some_and(|context| context.trigger_kind == CompletionTriggerKind::INVOKED);
+        let trigger_character = params
+            .context
+            .and_then(|c| c.trigger_character)
+            .and_then(|c| c.chars().next());
+
+        Q::Completion(CompletionRequest {
+            path,
+            position,
+            explicit,
+            trigger_character,
+        })
+    }
+
+    pub fn signature_help(params: SignatureHelpParams) -> Q {
+        let (path, position) = as_path_pos(params.text_document_position_params);
+        Q::SignatureHelp(SignatureHelpRequest { path, position })
+    }
+
+    pub fn rename(params: RenameParams) -> Q {
+        let (path, position) = as_path_pos(params.text_document_position);
+        let new_name = params.new_name;
+        Q::Rename(RenameRequest {
+            path,
+            position,
+            new_name,
+        })
+    }
+
+    pub fn prepare_rename(params: TextDocumentPositionParams) -> Q {
+        let (path, position) = as_path_pos(params);
+        Q::PrepareRename(PrepareRenameRequest { path, position })
+    }
+
+    pub fn symbol(params: WorkspaceSymbolParams) -> Q {
+        let pattern = (!params.query.is_empty()).then_some(params.query);
+        Q::Symbol(SymbolRequest { pattern })
+    }
+
+    pub fn on_enter(params: OnEnterParams) -> Q {
+        let path = as_path(params.text_document);
+        let range = params.range;
+        Q::OnEnter(OnEnterRequest { path, range })
+    }
+}
+
+macro_rules! query_source {
+    ($source:ident, $enc:ident, $method:ident, $req:expr) => {{
+        let source = $source($req.path.clone().into())?;
+        CompilerQueryResponse::$method($req.request(&source, $enc))
     }};
 }
 
@@ -318,35 +433,31 @@ impl ServerState {
         use CompilerQueryRequest::*;
 
         let is_pinning = self.pinning;
-        just_ok(match query {
-            FoldingRange(req) => query_source!(self, FoldingRange, req)?,
-            SelectionRange(req) => query_source!(self, SelectionRange, req)?,
-            DocumentSymbol(req) => query_source!(self, DocumentSymbol, req)?,
-            OnEnter(req) => query_source!(self, OnEnter, req)?,
-            ColorPresentation(req) => CompilerQueryResponse::ColorPresentation(req.request()),
-            OnExport(req) => return self.on_export(req),
-            ServerInfo(_) => return self.collect_server_info(),
+        match query {
+            FoldingRange(..)
+            | SelectionRange(..)
+            | DocumentSymbol(..)
+            | OnEnter(..)
+            | ColorPresentation(..) => just_result(self.query_context_free(query)),
+            OnExport(req) => self.on_export(req),
+            ServerInfo(_) => self.collect_server_info(),
             // todo: query on dedicate projects
-            _ => return self.query_on(is_pinning, query),
-        })
+            _ => self.query_on(is_pinning, query),
+        }
+    }
+
+    /// Performs a query that only needs the source synchronized with client.
+    fn query_context_free(&self, query: CompilerQueryRequest) -> Result<CompilerQueryResponse> {
+        let enc = self.const_config().position_encoding;
+        query_syntax(query, enc, |path| self.query_source(path, Ok))
     }
 
     fn query_on(&mut self, is_pinning: bool, query: CompilerQueryRequest) -> QueryFuture {
         use CompilerQueryRequest::*;
-        type R = CompilerQueryResponse;
         assert!(query.fold_feature() != FoldRequestFeature::ContextFreeUnique);
 
         let (mut snap, stat) = self.query_snapshot_with_stat(&query)?;
-        let input = query
-            .associated_path()
-            .map(|path| self.resolve_task(path.into()))
-            .or_else(|| {
-                let root = self.entry_resolver().root(None)?;
-                Some(TaskInputs {
-                    entry: Some(EntryState::new_rooted_by_id(root, *DETACHED_ENTRY)),
-                    ..Default::default()
-                })
-            });
+        let input = self.query_task(query.associated_path());
 
         just_future(async move {
             // todo: whether it is safe to inherit success_doc with changed entry
@@ -367,34 +478,324 @@ impl ServerState {
                 }
             }
 
+            snap.run_with_doc(|ctx, doc| query_analysis(ctx, doc, query))
+        })
+    }
+
+    /// Gets the task to run the query on the file.
+    fn query_task(&self, path: Option<&Path>) -> Option<TaskInputs> {
+        path.map(|path| self.resolve_task(path.into())).or_else(|| {
+            let root = self.entry_resolver().root(None)?;
+            Some(TaskInputs {
+                entry: Some(EntryState::new_rooted_by_id(root, *DETACHED_ENTRY)),
+                ..Default::default()
+            })
+        })
+    }
+}
+
+/// Performs a query that only needs the source of the file.
+fn query_syntax(
+    query: CompilerQueryRequest,
+    enc: PositionEncoding,
+    source: impl FnOnce(ImmutPath) -> Result<Source>,
+) -> Result<CompilerQueryResponse> {
+    use CompilerQueryRequest::*;
+
+    Ok(match query {
+        FoldingRange(req) => query_source!(source, enc, FoldingRange, req),
+        SelectionRange(req) => query_source!(source, enc, SelectionRange, req),
+        DocumentSymbol(req) => query_source!(source, enc, DocumentSymbol, req),
+        OnEnter(req) => query_source!(source, enc, OnEnter, req),
+        ColorPresentation(req) => CompilerQueryResponse::ColorPresentation(req.request()),
+        _ => unreachable!(),
+    })
+}
+
+/// Performs a query that needs the analysis context.
+fn query_analysis(
+    ctx: &mut LocalContext,
+    doc: Option<VersionedDocument>,
+    query: CompilerQueryRequest,
+) -> CompilerQueryResponse {
+    use CompilerQueryRequest::*;
+    type R = CompilerQueryResponse;
+
+    match query {
+        SemanticTokensFull(req) => R::SemanticTokensFull(req.request(ctx)),
+        SemanticTokensDelta(req) => R::SemanticTokensDelta(req.request(ctx)),
+        InteractCodeContext(req) => R::InteractCodeContext(req.request(ctx)),
+        Hover(req) => R::Hover(req.request(ctx, doc)),
+        GotoDefinition(req) => R::GotoDefinition(req.request(ctx, doc)),
+        GotoDeclaration(req) => R::GotoDeclaration(req.request(ctx)),
+        References(req) => R::References(req.request(ctx, doc)),
+        InlayHint(req) => R::InlayHint(req.request(ctx)),
+        DocumentHighlight(req) => R::DocumentHighlight(req.request(ctx)),
+        DocumentColor(req) => R::DocumentColor(req.request(ctx)),
+        DocumentLink(req) => R::DocumentLink(req.request(ctx)),
+        CodeAction(req) => R::CodeAction(req.request(ctx)),
+        CodeLens(req) => R::CodeLens(req.request(ctx)),
+        Completion(req) => R::Completion(req.request(ctx, doc)),
+        SignatureHelp(req) => R::SignatureHelp(req.request(ctx)),
+        Rename(req) => R::Rename(req.request(ctx, doc)),
+        WillRenameFiles(req) => R::WillRenameFiles(req.request(ctx, doc)),
+        PrepareRename(req) => R::PrepareRename(req.request(ctx, doc)),
+        Symbol(req) => R::Symbol(req.request(ctx)),
+        WorkspaceLabel(req) => R::WorkspaceLabel(req.request(ctx)),
+        DocumentMetrics(req) => R::DocumentMetrics(req.request(ctx, doc)),
+        _ => unreachable!(),
+    }
+}
+
+/// Batch Requests
+impl ServerState {
+    /// Runs multiple requests in one round trip.
+    ///
+    /// The requests needing analysis are run in the same analysis context on a
+    /// single snapshot, so their results are consistent with each other. They
+    /// must be on the files of the same entry, and the requests on the other
+    /// entries are rejected. The other requests are run on the sources
+    /// synchronized with client at the time the snapshot is taken.
+    pub(crate) fn batch(
+        &mut self,
+        req_id: RequestId,
+        params: Vec<BatchRequest>,
+    ) -> ScheduledResult {
+        let queries = params
+            .into_iter()
+            .map(|req| self.batch_query(req))
+            .collect::<Vec<_>>();
+
+        let mut responses = Vec::with_capacity(queries.len());
+        let mut analyzed = vec![];
+        // The task of the first request needing analysis, which is shared by
+        // the others.
+        let mut task: Option<(Option<EntryState>, Option<TaskInputs>)> = None;
+        for query in queries {
             match query {
-                SemanticTokensFull(req) => snap.run_semantic(req, R::SemanticTokensFull),
-                SemanticTokensDelta(req) => snap.run_semantic(req, R::SemanticTokensDelta),
-                InteractCodeContext(req) => snap.run_semantic(req, R::InteractCodeContext),
-                Hover(req) => snap.run_stateful(req, R::Hover),
-                GotoDefinition(req) => snap.run_stateful(req, R::GotoDefinition),
-                GotoDeclaration(req) => snap.run_semantic(req, R::GotoDeclaration),
-                References(req) => snap.run_stateful(req, R::References),
-                InlayHint(req) => snap.run_semantic(req, R::InlayHint),
-                DocumentHighlight(req) => snap.run_semantic(req, R::DocumentHighlight),
-                DocumentColor(req) => snap.run_semantic(req, R::DocumentColor),
-                DocumentLink(req) => snap.run_semantic(req, R::DocumentLink),
-                CodeAction(req) => snap.run_semantic(req, R::CodeAction),
-                CodeLens(req) => snap.run_semantic(req, R::CodeLens),
-                Completion(req) => snap.run_stateful(req, R::Completion),
-                SignatureHelp(req) => snap.run_semantic(req, R::SignatureHelp),
-                Rename(req) => snap.run_stateful(req, R::Rename),
-                WillRenameFiles(req) => snap.run_stateful(req, R::WillRenameFiles),
-                PrepareRename(req) => snap.run_stateful(req, R::PrepareRename),
-                Symbol(req) => snap.run_semantic(req, R::Symbol),
-                WorkspaceLabel(req) => snap.run_semantic(req, R::WorkspaceLabel),
-                DocumentMetrics(req) => snap.run_stateful(req, R::DocumentMetrics),
-                _ => unreachable!(),
+                Ok(query) if query.fold_feature() == FoldRequestFeature::ContextFreeUnique => {
+                    responses.push(Some(self.query_context_free(query).map_err(internal_error)));
+                }
+                Ok(query) => {
+                    let path = query.associated_path();
+                    // Focuses the entry as the requests do when sent alone.
+                    let site = match &query {
+                        CompilerQueryRequest::Hover(..) => Some('h'),
+                        CompilerQueryRequest::FoldingRange(..) => Some('f'),
+                        _ => None,
+                    };
+                    if let Some(site) = site {
+                        self.implicit_focus_entry(|| path.map(From::from), site);
+                    }
+                    let input = (!self.pinning).then(|| self.query_task(path)).flatten();
+                    let entry = input.as_ref().and_then(|input| input.entry.clone());
+                    let (first, _) = task.get_or_insert_with(|| (entry.clone(), input));
+                    if *first != entry {
+                        let err = "the request is on another entry than the previous requests \
+                                   in batch, send it in a separate batch";
+                        responses.push(Some(Err(invalid_params(err))));
+                        continue;
+                    }
+
+                    analyzed.push((responses.len(), query));
+                    responses.push(None);
+                }
+                Err(err) => responses.push(Some(Err(err))),
+            }
+        }
+
+        let snap = match (analyzed.first(), task) {
+            (Some((_, query)), Some((_, input))) => {
+                let stat = self
+                    .project
+                    .stats
+                    .query_stat(query.associated_path(), "Batch");
+                let snap = self.project.query_snapshot(Some(query));
+                Some((snap, stat, input))
+            }
+            _ => None,
+        };
+
+        let fut: SchedulableResponse<Vec<BatchResponse>> = just_future(async move {
+            if let Some((snap, stat, input)) = snap {
+                let results = snap.map_err(internal_error).and_then(|mut snap| {
+                    if let Some(input) = input {
+                        snap = snap.task(input);
+                    }
+                    stat.snap();
+
+                    snap.run_with_doc(|ctx, doc| {
+                        let queries = analyzed.into_iter();
+                        let results = queries
+                            .map(|(idx, query)| (idx, query_analysis(ctx, doc.clone(), query)));
+                        results.collect::<Vec<_>>()
+                    })
+                    .map_err(internal_error)
+                });
+
+                match results {
+                    Ok(results) => {
+                        for (idx, res) in results {
+                            responses[idx] = Some(Ok(res));
+                        }
+                    }
+                    Err(err) => {
+                        for res in responses.iter_mut().filter(|res| res.is_none()) {
+                            *res = Some(Err(err.clone()));
+                        }
+                    }
+                }
+            }
+
+            Ok(responses
+                .into_iter()
+                .flatten()
+                .map(BatchResponse::new)
+                .collect())
+        });
+
+        self.client.schedule(req_id, fut)
+    }
+
+    /// Converts a request in the batch to a query.
+    fn batch_query(&self, req: BatchRequest) -> LspResult<CompilerQueryRequest> {
+        use lsp_types::request::{self as lsp, Request};
+
+        fn parse<R: Request>(params: JsonValue) -> LspResult<R::Params> {
+            serde_json::from_value(params)
+                .map_err(|err| invalid_params(format!("invalid params of {}: {err}", R::METHOD)))
+        }
+
+        let params = req.params;
+        Ok(match req.method.as_str() {
+            lsp::HoverRequest::METHOD => to_query::hover(parse::<lsp::HoverRequest>(params)?),
+            lsp::GotoDefinition::METHOD => {
+                to_query::goto_definition(parse::<lsp::GotoDefinition>(params)?)
+            }
+            lsp::GotoDeclaration::METHOD => {
+                to_query::goto_declaration(parse::<lsp::GotoDeclaration>(params)?)
+            }
+            lsp::References::METHOD => to_query::references(parse::<lsp::References>(params)?),
+            lsp::DocumentHighlightRequest::METHOD => {
+                to_query::document_highlight(parse::<lsp::DocumentHighlightRequest>(params)?)
+            }
+            lsp::SignatureHelpRequest::METHOD => {
+                to_query::signature_help(parse::<lsp::SignatureHelpRequest>(params)?)
+            }
+            lsp::PrepareRenameRequest::METHOD => {
+                to_query::prepare_rename(parse::<lsp::PrepareRenameRequest>(params)?)
+            }
+            lsp::Rename::METHOD => to_query::rename(parse::<lsp::Rename>(params)?),
+            lsp::Completion::METHOD => to_query::completion(parse::<lsp::Completion>(params)?),
+            lsp::InlayHintRequest::METHOD => {
+                to_query::inlay_hint(parse::<lsp::InlayHintRequest>(params)?)
+            }
+            lsp::CodeActionRequest::METHOD => {
+                to_query::code_action(parse::<lsp::CodeActionRequest>(params)?)
+            }
+            lsp::CodeLensRequest::METHOD => {
+                to_query::code_lens(parse::<lsp::CodeLensRequest>(params)?)
+            }
+            lsp::DocumentColor::METHOD => {
+                to_query::document_color(parse::<lsp::DocumentColor>(params)?)
+            }
+            lsp::DocumentLinkRequest::METHOD => {
+                to_query::document_link(parse::<lsp::DocumentLinkRequest>(params)?)
             }
+            lsp::ColorPresentationRequest::METHOD => {
+                to_query::color_presentation(parse::<lsp::ColorPresentationRequest>(params)?)
+            }
+            lsp::DocumentSymbolRequest::METHOD => {
+                to_query::document_symbol(parse::<lsp::DocumentSymbolRequest>(params)?)
+            }
+            lsp::FoldingRangeRequest::METHOD => {
+                let params = parse::<lsp::FoldingRangeRequest>(params)?;
+                to_query::folding_range(params, self.const_config().doc_line_folding_only)
+            }
+            lsp::SelectionRangeRequest::METHOD => {
+                to_query::selection_range(parse::<lsp::SelectionRangeRequest>(params)?)
+            }
+            lsp::WorkspaceSymbolRequest::METHOD => {
+                to_query::symbol(parse::<lsp::WorkspaceSymbolRequest>(params)?)
+            }
+            OnEnter::METHOD => to_query::on_enter(parse::<OnEnter>(params)?),
+            // Semantic tokens are stateful across requests, and the others have
+            // side effects.
+            method => return Err(invalid_params(format!("{method} is not allowed in batch"))),
         })
     }
 }
 
+/// Snapshot Pinning
+impl ServerState {
+    /// Pins a snapshot for the follow-up requests, which are run on the same
+    /// world regardless of the changes and compilations happening after it.
+    pub(crate) fn pin_snapshot(
+        &mut self,
+        params: PinSnapshotParams,
+    ) -> SchedulableResponse<PinSnapshotResult> {
+        let mut snap = self.snapshot().map_err(internal_error)?;
+        if !self.pinning {
+            let path = params.text_document.map(as_path);
+            if let Some(input) = self.query_task(path.as_deref()) {
+                snap = snap.task(input);
+            }
+        }
+
+        let snap = self.project.query_snapshot_on(snap, None);
+        let token = self.pinned_snapshots.pin(snap);
+        let token = token.map_err(invalid_request)?;
+        log::info!("pinned snapshot {token}");
+
+        just_ok(PinSnapshotResult { token })
+    }
+
+    /// Runs a request on a pinned snapshot.
+    pub(crate) fn snapshot_query(
+        &mut self,
+        req_id: RequestId,
+        params: SnapshotQueryParams,
+    ) -> ScheduledResult {
+        let token = &params.token;
+        let snap = self.pinned_snapshots.get(token);
+        let snap = snap.ok_or_else(|| invalid_params(format!("snapshot {token} is not pinned")))?;
+
+        let query = self.batch_query(params.request)?;
+        let enc = self.const_config().position_encoding;
+        let name: &'static str = (&query).into();
+        let stat = self.project.stats.query_stat(query.associated_path(), name);
+
+        let query_fut = just_future(async move {
+            stat.snap();
+
+            if query.fold_feature() == FoldRequestFeature::ContextFreeUnique {
+                let world = &snap.world;
+                return query_syntax(query, enc, |path| {
+                    let source = world.source_by_path(&path);
+                    source.map_err(|err| anyhow::anyhow!("file missing {path:?}: {err}").into())
+                });
+            }
+
+            snap.run_with_doc(|ctx, doc| query_analysis(ctx, doc, query))
+        });
+        self.client.untyped().schedule_query(req_id, query_fut)
+    }
+
+    /// Releases a pinned snapshot.
+    pub(crate) fn release_snapshot(
+        &mut self,
+        params: ReleaseSnapshotParams,
+    ) -> SchedulableResponse<()> {
+        let token = &params.token;
+        if !self.pinned_snapshots.release(token) {
+            return Err(invalid_params(format!("snapshot {token} is not pinned")));
+        }
+        log::info!("released snapshot {token}");
+
+        just_ok(())
+    }
+}
+
 /// A parameter for the `experimental/onEnter` command.
 ///
 /// @since 3.17.0
@@ -414,3 +815,111 @@ impl lsp_types::request::Request for OnEnter {
     type Result = Option<Vec<TextEdit>>;
     const METHOD: &'static str = "experimental/onEnter";
 }
+
+/// A request in the `tinymist/batch` request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BatchRequest {
+    /// The method of the request, e.g. `textDocument/hover`.
+    pub method: String,
+    /// The parameters of the request.
+    #[serde(default)]
+    pub params: JsonValue,
+}
+
+/// The response to a request in the `tinymist/batch` request, which is either
+/// a result or an error.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BatchResponse {
+    /// The result of the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<JsonValue>,
+    /// The error of the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ResponseError>,
+}
+
+impl BatchResponse {
+    fn new(res: LspResult<CompilerQueryResponse>) -> Self {
+        let res = res.and_then(|res| serde_json::to_value(res).map_err(internal_error));
+        match res {
+            Ok(result) => Self {
+                result: Some(result),
+                error: None,
+            },
+            Err(error) => Self {
+                result: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+/// Runs multiple requests on a single snapshot in one round trip, and returns
+/// their responses in the same order.
+pub struct Batch;
+impl lsp_types::request::Request for Batch {
+    type Params = Vec<BatchRequest>;
+    type Result = Vec<BatchResponse>;
+    const METHOD: &'static str = "tinymist/batch";
+}
+
+/// The parameters of the `tinymist/pinSnapshot` request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinSnapshotParams {
+    /// The document to resolve the entry of the snapshot. If it is not
+    /// specified, the snapshot is detached from any document, e.g. for
+    /// workspace symbols.
+    #[serde(default)]
+    pub text_document: Option<TextDocumentIdentifier>,
+}
+
+/// The result of the `tinymist/pinSnapshot` request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PinSnapshotResult {
+    /// The token identifying the pinned snapshot.
+    pub token: String,
+}
+
+/// Pins a snapshot for the follow-up requests, until it is released by the
+/// `tinymist/releaseSnapshot` request.
+pub struct PinSnapshot;
+impl lsp_types::request::Request for PinSnapshot {
+    type Params = PinSnapshotParams;
+    type Result = PinSnapshotResult;
+    const METHOD: &'static str = "tinymist/pinSnapshot";
+}
+
+/// The parameters of the `tinymist/snapshotQuery` request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SnapshotQueryParams {
+    /// The token of the pinned snapshot.
+    pub token: String,
+    /// The request to run on the snapshot.
+    #[serde(flatten)]
+    pub request: BatchRequest,
+}
+
+/// Runs a request on a pinned snapshot. The request is one of the requests
+/// allowed in the `tinymist/batch` request.
+pub struct SnapshotQuery;
+impl lsp_types::request::Request for SnapshotQuery {
+    type Params = SnapshotQueryParams;
+    type Result = JsonValue;
+    const METHOD: &'static str = "tinymist/snapshotQuery";
+}
+
+/// The parameters of the `tinymist/releaseSnapshot` request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReleaseSnapshotParams {
+    /// The token of the pinned snapshot.
+    pub token: String,
+}
+
+/// Releases a pinned snapshot.
+pub struct ReleaseSnapshot;
+impl lsp_types::request::Request for ReleaseSnapshot {
+    type Params = ReleaseSnapshotParams;
+    type Result = ();
+    const METHOD: &'static str = "tinymist/releaseSnapshot";
+}
@@ -11,7 +11,6 @@ use std::{
 use clap::Parser;
 use clap_builder::CommandFactory;
 use clap_complete::generate;
-use futures::future::MaybeDone;
 use lsp_server::RequestId;
 use once_cell::sync::Lazy;
 use reflexo::ImmutPath;
@@ -20,12 +19,12 @@ use serde_json::Value as JsonValue;
 use sync_lsp::{
     internal_error,
     transport::{with_stdio_transport, MirrorArgs},
-    LspBuilder, LspClientRoot, LspResult,
+    LspClientRoot, LspResult,
 };
 use tinymist::{tool::project::generate_script_main, world::TaskInputs};
 use tinymist::{
     tool::project::{compile_main, project_main, task_main},
-    CompileConfig, Config, RegularInit, ServerState, SuperInit, UserActionTask,
+    CompileConfig, Config, ServerStateBuilder, UserActionTask,
 };
 use tinymist_core::LONG_VERSION;
 use tinymist_project::EntryResolver;
@@ -126,16 +125,10 @@ pub fn lsp_main(args: LspArgs) -> Result<()> {
     let is_replay = !args.mirror.replay.is_empty();
     with_stdio_transport(args.mirror.clone(), |conn| {
         let client = LspClientRoot::new(RUNTIMES.tokio_runtime.handle().clone(), conn.sender);
-        ServerState::install(LspBuilder::new(
-            RegularInit {
-                client: client.weak().to_typed(),
-                font_opts: args.font,
-                exec_cmds: Vec::new(),
-            },
-            client.weak(),
-        ))
-        .build()
-        .start(conn.receiver, is_replay)
+        ServerStateBuilder::new(client.weak())
+            .with_font_opts(args.font)
+            .build()
+            .start(conn.receiver, is_replay)
     })?;
 
     log::info!("language server did shut down");
@@ -177,22 +170,9 @@ pub fn trace_lsp_main(args: TraceLspArgs) -> Result<()> {
             ..Config::default()
         };
 
-        let mut service = ServerState::install(LspBuilder::new(
-            SuperInit {
-                client: client.to_typed(),
-                exec_cmds: Vec::new(),
-                config,
-                err: None,
-            },
-            client.clone(),
-        ))
-        .build();
-
-        let resp = service.ready(()).unwrap();
-        let MaybeDone::Done(resp) = resp else {
-            anyhow::bail!("internal error: not sync init")
-        };
-        resp.unwrap();
+        let mut service = ServerStateBuilder::new(client.clone())
+            .with_config(config)
+            .build_ready()?;
 
         // todo: persist
         let request_received = reflexo::time::Instant::now();
@@ -239,22 +219,9 @@ pub fn query_main(cmds: QueryCommands) -> Result<()> {
         // todo: roots, inputs, font_opts
         let config = Config::default();
 
-        let mut service = ServerState::install(LspBuilder::new(
-            SuperInit {
-                client: client.to_typed(),
-                exec_cmds: Vec::new(),
-                config,
-                err: None,
-            },
-            client.clone(),
-        ))
-        .build();
-
-        let resp = service.ready(()).unwrap();
-        let MaybeDone::Done(resp) = resp else {
-            anyhow::bail!("internal error: not sync init")
-        };
-        resp.unwrap();
+        let mut service = ServerStateBuilder::new(client.clone())
+            .with_config(config)
+            .build_ready()?;
 
         let state = service.state_mut().unwrap();
 
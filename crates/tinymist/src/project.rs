@@ -22,6 +22,7 @@
 pub use tinymist_project::*;
 
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use parking_lot::Mutex;
 use reflexo::{hash::FxHashMap, path::unix_slash};
@@ -29,9 +30,8 @@ use reflexo_typst::CompileReport;
 use sync_lsp::{LspClient, TypedLspClient};
 use tinymist_project::vfs::{FileChangeSet, MemoryEvent};
 use tinymist_query::{
-    analysis::{Analysis, AnalysisRevLock, LocalContextGuard, PeriscopeProvider},
-    CompilerQueryRequest, CompilerQueryResponse, DiagnosticsMap, LocalContext, SemanticRequest,
-    StatefulRequest, VersionedDocument,
+    analysis::{Analysis, AnalysisRevLock, Definition, LocalContextGuard, PeriscopeProvider},
+    CompilerQueryRequest, CompilerQueryResponse, DiagnosticsMap, LocalContext, VersionedDocument,
 };
 use tinymist_render::PeriscopeRenderer;
 use tinymist_std::{error::prelude::*, ImmutPath};
@@ -39,9 +39,11 @@ use tokio::sync::mpsc;
 use typst::{diag::FileResult, foundations::Bytes, layout::Position as TypstPosition};
 
 use super::ServerState;
-use crate::actor::editor::{CompileStatus, CompileStatusEnum, EditorRequest, ProjVersion};
+use crate::actor::editor::{
+    CompileStatus, CompileStatusEnum, DiagStage, EditorRequest, ProjVersion,
+};
 use crate::stats::{CompilerQueryStats, QueryStatGuard};
-use crate::{task::ExportUserConfig, Config};
+use crate::{task::ExportUserConfig, Config, Derived};
 
 type EditorSender = mpsc::UnboundedSender<EditorRequest>;
 
@@ -83,12 +85,17 @@ impl ServerState {
         #[cfg(feature = "preview")]
         self.preview.stop_all();
 
+        #[cfg(feature = "preview")]
         let watchers = self.preview.watchers.clone();
+        #[cfg(not(feature = "preview"))]
+        let watchers = ProjectPreviewState::default();
         let editor_tx = self.editor_tx.clone();
 
         let new_project = Self::project(&self.config, editor_tx, self.client.clone(), watchers);
 
         let mut old_project = std::mem::replace(&mut self.project, new_project);
+        // The pinned snapshots belong to the old project.
+        self.pinned_snapshots.clear();
 
         // todo: the old dedicate projects should be transferred.
 
@@ -141,7 +148,6 @@ impl ServerState {
         // Create the compile handler for client consuming results.
         let periscope_args = config.compile.periscope_args.clone();
         let handle = Arc::new(CompileHandlerImpl {
-            #[cfg(feature = "preview")]
             preview,
             export: export.clone(),
             editor_tx: editor_tx.clone(),
@@ -178,11 +184,19 @@ impl ServerState {
 
         log::info!("ServerState: creating ProjectState, entry: {entry:?}, inputs: {inputs:?}");
 
-        // todo: never fail?
-        let embedded_fonts = Arc::new(LspUniverseBuilder::only_embedded_fonts().unwrap());
-        let package_registry =
-            LspUniverseBuilder::resolve_package(cert_path.clone(), Some(&package));
-        let verse = LspUniverseBuilder::build(entry, inputs, embedded_fonts, package_registry);
+        let verse = match config.compile.universe.as_ref() {
+            Some(Derived(factory)) => factory(&config.compile),
+            None => {
+                // todo: never fail?
+                let embedded_fonts = Arc::new(LspUniverseBuilder::only_embedded_fonts().unwrap());
+                let package_registry =
+                    LspUniverseBuilder::resolve_package(cert_path.clone(), Some(&package));
+                LspUniverseBuilder::build(entry, inputs, embedded_fonts, package_registry)
+            }
+        };
+        let verse = verse
+            .with_plugin_policy(config.compile.plugin.policy())
+            .with_font_fallback(config.compile.font_fallback.clone());
 
         // todo: unify filesystem watcher
         let (dep_tx, dep_rx) = mpsc::unbounded_channel();
@@ -204,13 +218,15 @@ impl ServerState {
             },
         );
 
-        // Delayed Loads fonts
-        let font_client = client.clone();
-        let font_resolver = config.compile.determine_fonts();
-        client.handle.spawn_blocking(move || {
-            // Refresh fonts
-            font_client.send_event(LspInterrupt::Font(font_resolver.wait().clone()));
-        });
+        // Delayed Loads fonts, unless the universe is provided by the embedder.
+        if config.compile.universe.is_none() {
+            let font_client = client.clone();
+            let font_resolver = config.compile.determine_fonts();
+            client.handle.spawn_blocking(move || {
+                // Refresh fonts
+                font_client.send_event(LspInterrupt::Font(font_resolver.wait().clone()));
+            });
+        }
 
         ProjectState {
             compiler,
@@ -226,6 +242,8 @@ impl ServerState {
 pub struct ProjectInsStateExt {
     pub is_compiling: bool,
     pub last_compilation: Option<LspCompiledArtifact>,
+    /// The revision of the last published syntax errors.
+    pub syntax_revision: usize,
 }
 
 pub struct ProjectState {
@@ -250,14 +268,24 @@ impl ProjectState {
     /// Snapshot the compiler thread for language queries
     pub fn query_snapshot(&mut self, q: Option<&CompilerQueryRequest>) -> Result<LspQuerySnapshot> {
         let snap = self.snapshot()?;
+        Ok(self.query_snapshot_on(snap, q))
+    }
+
+    /// Snapshot for language queries on a compile snapshot, e.g. a snapshot
+    /// pinned by the client.
+    pub fn query_snapshot_on(
+        &self,
+        snap: LspCompileSnapshot,
+        q: Option<&CompilerQueryRequest>,
+    ) -> LspQuerySnapshot {
         let analysis = self.analysis.clone();
         let rev_lock = analysis.lock_revision(q);
 
-        Ok(LspQuerySnapshot {
+        LspQuerySnapshot {
             snap,
             analysis,
             rev_lock,
-        })
+        }
     }
 
     pub fn interrupt(&mut self, intr: Interrupt<LspCompilerFeat>) {
@@ -297,6 +325,16 @@ impl PeriscopeProvider for TypstPeriscopeProvider {
     ) -> Option<String> {
         self.0.render_marked(ctx, doc, pos)
     }
+
+    /// Resolve periscope image at where the definition is rendered.
+    fn periscope_definition(
+        &self,
+        ctx: &mut LocalContext,
+        doc: VersionedDocument,
+        def: &Definition,
+    ) -> Option<String> {
+        self.0.render_definition_marked(ctx, doc, def)
+    }
 }
 
 #[derive(Default, Clone)]
@@ -360,12 +398,52 @@ impl ProjectClient for mpsc::UnboundedSender<LspInterrupt> {
 }
 
 impl CompileHandlerImpl {
-    fn push_diagnostics(&self, dv: ProjVersion, diagnostics: Option<DiagnosticsMap>) {
+    fn push_diagnostics(
+        &self,
+        dv: ProjVersion,
+        stage: DiagStage,
+        diagnostics: Option<DiagnosticsMap>,
+    ) {
         self.editor_tx
-            .send(EditorRequest::Diag(dv, diagnostics))
+            .send(EditorRequest::Diag(dv, stage, diagnostics))
             .log_error("failed to send diagnostics");
     }
 
+    /// Publishes the syntax errors of the project right after the sources are
+    /// edited, without waiting for the compilation.
+    fn notify_syntax(&self, s: &mut ProjectInsState<LspCompilerFeat, ProjectInsStateExt>) {
+        let revision = s.verse.revision.get();
+        if s.ext.syntax_revision >= revision || s.verse.entry_state().is_inactive() {
+            return;
+        }
+        s.ext.syntax_revision = revision;
+
+        let world = s.verse.snapshot();
+        let mut ids = (s.ext.last_compilation.as_ref())
+            .map(|c| c.depended_files().clone())
+            .unwrap_or_default();
+        if let Some(main) = world.main_id().filter(|main| !ids.contains(main)) {
+            ids.push(main);
+        }
+
+        let dv = ProjVersion {
+            id: s.id.clone(),
+            revision,
+        };
+        let editor_tx = self.editor_tx.clone();
+        let position_encoding = self.analysis.position_encoding;
+        rayon::spawn(move || {
+            let diagnostics = tinymist_query::convert_syntax_errors(&world, ids, position_encoding);
+            editor_tx
+                .send(EditorRequest::Diag(
+                    dv,
+                    DiagStage::Syntax,
+                    Some(diagnostics),
+                ))
+                .log_error("failed to send diagnostics");
+        });
+    }
+
     fn notify_diagnostics(&self, snap: &LspCompiledArtifact) {
         let world = &snap.world;
         let dv = ProjVersion {
@@ -376,20 +454,49 @@ impl CompileHandlerImpl {
         // todo: better way to remove diagnostics
         // todo: check all errors in this file
         let valid = !world.entry_state().is_inactive();
+        let syntax_errors = valid.then(|| {
+            tinymist_query::convert_syntax_errors(
+                world,
+                snap.depended_files().iter().copied(),
+                self.analysis.position_encoding,
+            )
+        });
+        self.push_diagnostics(dv.clone(), DiagStage::Syntax, syntax_errors);
+
         let diagnostics = valid.then(|| {
+            // The syntax errors are published in the syntax stage.
             let errors = snap.doc.as_ref().err().into_iter().flatten();
+            let errors = errors.filter(|err| !tinymist_query::is_syntax_error(world, err));
             let warnings = snap.warnings.as_ref();
-            let diagnostics = tinymist_query::convert_diagnostics(
+            let mut diagnostics = tinymist_query::convert_diagnostics(
                 world,
                 errors.chain(warnings),
                 self.analysis.position_encoding,
             );
+            let show_rule_issues = tinymist_query::convert_show_rule_issues(
+                world,
+                snap.depended_files().iter().copied(),
+                self.analysis.position_encoding,
+            );
+            let plugin_stubs = tinymist_query::convert_plugin_stubs(
+                world,
+                snap.depended_files().iter().copied(),
+                self.analysis.position_encoding,
+            );
+            let font_fallback_issues = tinymist_query::convert_font_fallback_issues(
+                world,
+                self.analysis.position_encoding,
+            );
+            let issues = show_rule_issues.into_iter().chain(plugin_stubs);
+            for (uri, issues) in issues.chain(font_fallback_issues) {
+                diagnostics.entry(uri).or_default().extend(issues);
+            }
 
             log::trace!("notify diagnostics({dv:?}): {diagnostics:#?}");
             diagnostics
         });
 
-        self.push_diagnostics(dv, diagnostics);
+        self.push_diagnostics(dv, DiagStage::Compile, diagnostics);
     }
 }
 
@@ -397,6 +504,10 @@ impl CompileHandler<LspCompilerFeat, ProjectInsStateExt> for CompileHandlerImpl
     fn on_any_compile_reason(&self, c: &mut LspProjectCompiler) {
         let instances_mut = std::iter::once(&mut c.primary).chain(c.dedicates.iter_mut());
         for s in instances_mut {
+            if s.reason.by_memory_events {
+                self.notify_syntax(s);
+            }
+
             if s.ext.is_compiling {
                 continue;
             }
@@ -446,7 +557,8 @@ impl CompileHandler<LspCompilerFeat, ProjectInsStateExt> for CompileHandlerImpl
                     id: id.clone(),
                     revision,
                 };
-                self.push_diagnostics(dv, None);
+                self.push_diagnostics(dv.clone(), DiagStage::Syntax, None);
+                self.push_diagnostics(dv, DiagStage::Compile, None);
                 CompileStatusEnum::CompileSuccess
             }
             CompileReport::Stage(_, _, _) => CompileStatusEnum::Compiling,
@@ -555,25 +667,28 @@ impl LspQuerySnapshot {
         self
     }
 
-    pub fn run_stateful<T: StatefulRequest>(
+    /// Forks the snapshot, which is analyzed on the same revision.
+    pub fn fork(&self) -> Self {
+        Self {
+            snap: self.snap.clone(),
+            analysis: self.analysis.clone(),
+            rev_lock: self.rev_lock.fork(),
+        }
+    }
+
+    /// Runs the analysis with the last successfully compiled document.
+    ///
+    /// All requests run in the closure share the same analysis context, so
+    /// their results are consistent with each other.
+    pub fn run_with_doc<T>(
         self,
-        query: T,
-        wrapper: fn(Option<T::Response>) -> CompilerQueryResponse,
-    ) -> Result<CompilerQueryResponse> {
+        f: impl FnOnce(&mut LocalContextGuard, Option<VersionedDocument>) -> T,
+    ) -> Result<T> {
         let doc = self.snap.success_doc.as_ref().map(|doc| VersionedDocument {
             version: self.world.revision().get(),
             document: doc.clone(),
         });
-        self.run_analysis(|ctx| query.request(ctx, doc))
-            .map(wrapper)
-    }
-
-    pub fn run_semantic<T: SemanticRequest>(
-        self,
-        query: T,
-        wrapper: fn(Option<T::Response>) -> CompilerQueryResponse,
-    ) -> Result<CompilerQueryResponse> {
-        self.run_analysis(|ctx| query.request(ctx)).map(wrapper)
+        self.run_analysis(|ctx| f(ctx, doc))
     }
 
     pub fn run_analysis<T>(self, f: impl FnOnce(&mut LocalContextGuard) -> T) -> Result<T> {
@@ -587,3 +702,77 @@ impl LspQuerySnapshot {
         Ok(f(&mut analysis))
     }
 }
+
+/// The maximum number of snapshots pinned by the client at the same time.
+const MAX_PINNED_SNAPSHOTS: usize = 16;
+/// The time after which a pinned snapshot not used is released, in case the
+/// client forgets to release it.
+const PINNED_SNAPSHOT_TTL: Duration = Duration::from_secs(60);
+
+/// The snapshots pinned by the client, which are identified by tokens.
+#[derive(Default)]
+pub struct PinnedSnapshots {
+    /// The id of the next pinned snapshot.
+    next_id: usize,
+    /// The pinned snapshots.
+    snapshots: FxHashMap<String, PinnedSnapshot>,
+}
+
+struct PinnedSnapshot {
+    /// The pinned snapshot, which also keeps the analysis caches of the
+    /// revision locked at pin time until it is released.
+    snap: LspQuerySnapshot,
+    /// The last time the snapshot was pinned or used.
+    last_used: Instant,
+}
+
+impl PinnedSnapshots {
+    /// Pins a snapshot and returns its token.
+    pub fn pin(&mut self, snap: LspQuerySnapshot) -> Result<String> {
+        self.expire();
+        if self.snapshots.len() >= MAX_PINNED_SNAPSHOTS {
+            bail!("too many pinned snapshots, release some of them first");
+        }
+
+        self.next_id += 1;
+        let token = format!("snapshot-{}", self.next_id);
+        let pinned = PinnedSnapshot {
+            snap,
+            last_used: Instant::now(),
+        };
+        self.snapshots.insert(token.clone(), pinned);
+
+        Ok(token)
+    }
+
+    /// Gets a pinned snapshot by its token, which is analyzed on the revision
+    /// locked at pin time.
+    pub fn get(&mut self, token: &str) -> Option<LspQuerySnapshot> {
+        self.expire();
+        let pinned = self.snapshots.get_mut(token)?;
+        pinned.last_used = Instant::now();
+        Some(pinned.snap.fork())
+    }
+
+    /// Releases a pinned snapshot, returning whether it was pinned.
+    pub fn release(&mut self, token: &str) -> bool {
+        self.expire();
+        self.snapshots.remove(token).is_some()
+    }
+
+    /// Releases all the pinned snapshots.
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    /// Releases the snapshots not used for a while.
+    pub fn expire(&mut self) {
+        self.snapshots.retain(|token, pinned| {
+            let alive = pinned.last_used.elapsed() < PINNED_SNAPSHOT_TTL;
+            if !alive {
+                log::info!("released expired snapshot {token}");
+            }
+            alive
+        });
+    }
+}
@@ -13,9 +13,9 @@ use tokio::sync::mpsc;
 use typst::syntax::Source;
 
 use crate::actor::editor::{EditorActor, EditorRequest};
-use crate::lsp_query::OnEnter;
+use crate::lsp_query::{Batch, OnEnter, PinSnapshot, ReleaseSnapshot, SnapshotQuery};
 use crate::project::{
-    update_lock, LspInterrupt, ProjectPreviewState, ProjectState,
+    update_lock, LspInterrupt, PinnedSnapshots, ProjectPreviewState, ProjectState,
     PROJECT_ROUTE_USER_ACTION_PRIORITY,
 };
 use crate::route::ProjectRouteState;
@@ -64,6 +64,8 @@ pub struct ServerState {
     pub formatter_registered: bool,
     /// Whether client is pinning a file.
     pub pinning: bool,
+    /// The analysis snapshots pinned by the client.
+    pub pinned_snapshots: PinnedSnapshots,
     /// The client focusing file.
     pub focusing: Option<ImmutPath>,
     /// The client ever focused implicitly by activities.
@@ -108,6 +110,7 @@ impl ServerState {
             config,
 
             pinning: false,
+            pinned_snapshots: PinnedSnapshots::default(),
             focusing: None,
             formatter,
             user_action: Default::default(),
@@ -159,19 +162,40 @@ impl ServerState {
     /// Installs handlers to the language server.
     pub fn install<T: Initializer<S = Self> + AddCommands + 'static>(
         provider: LspBuilder<T>,
+    ) -> LspBuilder<T> {
+        Self::install_with(provider, ServerFeatures::default())
+    }
+
+    /// Installs handlers of the enabled features to the language server.
+    pub fn install_with<T: Initializer<S = Self> + AddCommands + 'static>(
+        mut provider: LspBuilder<T>,
+        features: ServerFeatures,
     ) -> LspBuilder<T> {
         type State = ServerState;
         use lsp_types::notification::*;
         use lsp_types::request::*;
 
         #[cfg(feature = "preview")]
-        let provider = provider
-            .with_command("tinymist.doStartPreview", State::start_preview)
-            .with_command("tinymist.doKillPreview", State::kill_preview)
-            .with_command("tinymist.scrollPreview", State::scroll_preview);
+        if features.preview {
+            provider = provider
+                .with_command("tinymist.doStartPreview", State::start_preview)
+                .with_command("tinymist.doKillPreview", State::kill_preview)
+                .with_command("tinymist.scrollPreview", State::scroll_preview);
+        }
+
+        if features.export {
+            provider = provider
+                .with_command_("tinymist.exportPdf", State::export_pdf)
+                .with_command_("tinymist.exportSvg", State::export_svg)
+                .with_command_("tinymist.exportPng", State::export_png)
+                .with_command_("tinymist.exportText", State::export_text)
+                .with_command_("tinymist.exportHtml", State::export_html)
+                .with_command_("tinymist.exportMarkdown", State::export_markdown)
+                .with_command_("tinymist.exportQuery", State::export_query);
+        }
 
         // todo: .on_sync_mut::<notifs::Cancel>(handlers::handle_cancel)?
-        let mut provider = provider
+        provider = provider
             .with_request::<Shutdown>(State::shutdown)
             // customized event
             .with_event(
@@ -205,6 +229,10 @@ impl ServerState {
             .with_request_::<WorkspaceSymbolRequest>(State::symbol)
             .with_request_::<OnEnter>(State::on_enter)
             .with_request_::<WillRenameFiles>(State::will_rename_files)
+            .with_request_::<Batch>(State::batch)
+            .with_request::<PinSnapshot>(State::pin_snapshot)
+            .with_request_::<SnapshotQuery>(State::snapshot_query)
+            .with_request::<ReleaseSnapshot>(State::release_snapshot)
             // notifications
             .with_notification::<Initialized>(State::initialized)
             .with_notification::<DidOpenTextDocument>(State::did_open)
@@ -213,13 +241,6 @@ impl ServerState {
             .with_notification::<DidSaveTextDocument>(State::did_save)
             .with_notification::<DidChangeConfiguration>(State::did_change_configuration)
             // commands
-            .with_command_("tinymist.exportPdf", State::export_pdf)
-            .with_command_("tinymist.exportSvg", State::export_svg)
-            .with_command_("tinymist.exportPng", State::export_png)
-            .with_command_("tinymist.exportText", State::export_text)
-            .with_command_("tinymist.exportHtml", State::export_html)
-            .with_command_("tinymist.exportMarkdown", State::export_markdown)
-            .with_command_("tinymist.exportQuery", State::export_query)
             .with_command("tinymist.exportAnsiHighlight", State::export_ansi_hl)
             .with_command("tinymist.doClearCache", State::clear_cache)
             .with_command("tinymist.pinMain", State::pin_document)
@@ -266,6 +287,8 @@ impl ServerState {
             return Ok(());
         };
 
+        // Releases the snapshots the client forgot to release.
+        ready.pinned_snapshots.expire();
         ready.project.interrupt(params);
         // log::info!("interrupted in {:?}", _start.elapsed());
         Ok(())
@@ -334,18 +357,9 @@ impl ServerState {
                 tokio::spawn(update_dep(snap));
             }
 
-            // See https://github.com/Myriad-Dreamin/tinymist/issues/837
-            // Also see https://github.com/Byron/open-rs/issues/105
-            #[cfg(not(target_os = "windows"))]
-            let do_open = ::open::that_detached;
-            #[cfg(target_os = "windows")]
-            fn do_open(path: impl AsRef<std::ffi::OsStr>) -> std::io::Result<()> {
-                ::open::with_detached(path, "explorer")
-            }
-
             if let Some(Some(path)) = open.then_some(res.as_ref()) {
                 log::info!("open with system default apps: {path:?}");
-                do_open(path).log_error("failed to open with system default apps");
+                open_with_system(path).log_error("failed to open with system default apps");
             }
 
             log::info!("CompileActor: on export end: {path:?} as {res:?}");
@@ -354,6 +368,27 @@ impl ServerState {
     }
 }
 
+/// Opens the file with the system default app.
+#[cfg(feature = "open")]
+fn open_with_system(path: &Path) -> std::io::Result<()> {
+    // See https://github.com/Myriad-Dreamin/tinymist/issues/837
+    // Also see https://github.com/Byron/open-rs/issues/105
+    if cfg!(target_os = "windows") {
+        ::open::with_detached(path, "explorer")
+    } else {
+        ::open::that_detached(path)
+    }
+}
+
+/// Opens the file with the system default app.
+#[cfg(not(feature = "open"))]
+fn open_with_system(_path: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "the server is built without the `open` feature",
+    ))
+}
+
 #[test]
 fn test_as_path() {
     use reflexo::path::PathClean;
@@ -1,27 +1,32 @@
 //! The actor that handles various document export, like PDF and SVG export.
 
-use std::str::FromStr;
 use std::{path::PathBuf, sync::Arc};
 
 use crate::project::{
-    ApplyProjectTask, CompiledArtifact, ExportHtmlTask, ExportMarkdownTask, ExportPdfTask,
-    ExportPngTask, ExportTextTask, TaskWhen,
+    ApplyProjectTask, CompiledArtifact, ExportHtmlTask, ExportPdfTask, ExportTextTask, TaskWhen,
 };
 use anyhow::bail;
 use reflexo::ImmutPath;
-use reflexo_typst::{TypstAbs as Abs, TypstDatetime};
+use reflexo_typst::TypstAbs as Abs;
 use tinymist_project::{
-    convert_source_date_epoch, EntryReader, ExportSvgTask, ExportTask as ProjectExportTask,
-    ExportTransform, LspCompiledArtifact, Pages, ProjectTask, QueryTask,
+    EntryReader, ExportTask as ProjectExportTask, ExportTransform, LspCompiledArtifact, Pages,
+    ProjectTask, QueryTask,
 };
 use tinymist_std::error::prelude::*;
 use tinymist_std::typst::TypstDocument;
 use tokio::sync::mpsc;
-use typlite::Typlite;
 use typst::foundations::IntoValue;
 use typst::syntax::{ast, SyntaxNode};
-use typst::visualize::Color;
-use typst_pdf::PdfOptions;
+#[cfg(feature = "export")]
+use {
+    crate::project::{ExportMarkdownTask, ExportPngTask, ExportSvgTask},
+    reflexo_typst::TypstDatetime,
+    std::str::FromStr,
+    tinymist_project::convert_source_date_epoch,
+    typlite::Typlite,
+    typst::visualize::Color,
+    typst_pdf::PdfOptions,
+};
 
 use crate::tool::text::FullTextDigest;
 use crate::{actor::editor::EditorRequest, tool::word_count};
@@ -188,10 +193,16 @@ impl ExportTask {
 
             // static BLANK: Lazy<Page> = Lazy::new(Page::default);
             let TypstDocument::Paged(paged_doc) = &doc;
+            #[cfg(feature = "export")]
             let first_page = paged_doc.pages.first().unwrap();
             Ok(match kind2 {
                 Preview(..) => vec![],
+                #[cfg(not(feature = "export"))]
+                ExportPdf(..) | ExportSvg(..) | ExportPng(..) | ExportMarkdown(..) => {
+                    bail!("the export feature is not enabled in this build")
+                }
                 // todo: more pdf flags
+                #[cfg(feature = "export")]
                 ExportPdf(ExportPdfTask {
                     creation_timestamp, ..
                 }) => {
@@ -251,6 +262,7 @@ impl ExportTask {
                 ExportText(ExportTextTask { export: _ }) => {
                     format!("{}", FullTextDigest(doc.clone())).into_bytes()
                 }
+                #[cfg(feature = "export")]
                 ExportMarkdown(ExportMarkdownTask { export: _ }) => {
                     let conv = Typlite::new(Arc::new(snap.world))
                         .convert()
@@ -258,6 +270,7 @@ impl ExportTask {
 
                     conv.as_bytes().to_owned()
                 }
+                #[cfg(feature = "export")]
                 ExportSvg(ExportSvgTask { export }) => {
                     let (is_first, merged_gap) = get_page_selection(&export)?;
 
@@ -267,6 +280,7 @@ impl ExportTask {
                         typst_svg::svg_merged(paged_doc, merged_gap).into_bytes()
                     }
                 }
+                #[cfg(feature = "export")]
                 ExportPng(ExportPngTask { export, ppi, fill }) => {
                     let ppi = ppi.to_f32();
                     if ppi <= 1e-6 {
@@ -327,6 +341,7 @@ impl Default for ExportUserConfig {
     }
 }
 
+#[cfg(feature = "export")]
 fn parse_color(fill: String) -> anyhow::Result<Color> {
     match fill.as_str() {
         "black" => Ok(Color::BLACK),
@@ -352,6 +367,7 @@ fn log_err<T>(artifact: anyhow::Result<T>) -> Option<T> {
 }
 
 /// Convert [`chrono::DateTime`] to [`TypstDatetime`]
+#[cfg(feature = "export")]
 fn convert_datetime(date_time: chrono::DateTime<chrono::Utc>) -> Option<TypstDatetime> {
     use chrono::{Datelike, Timelike};
     TypstDatetime::from_ymd_hms(
@@ -461,6 +477,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "export")]
     fn test_parse_color() {
         assert_eq!(parse_color("black".to_owned()).unwrap(), Color::BLACK);
         assert_eq!(parse_color("white".to_owned()).unwrap(), Color::WHITE);
@@ -4,7 +4,9 @@ use std::path::PathBuf;
 
 use anyhow::bail;
 use base64::Engine;
+#[cfg(feature = "trace-server")]
 use hyper::service::service_fn;
+#[cfg(feature = "trace-server")]
 use hyper_util::{rt::TokioIo, server::graceful::GracefulShutdown};
 use lsp_server::RequestId;
 use reflexo_typst::{CompileEnv, Compiler, TypstDict};
@@ -199,6 +201,7 @@ async fn trace_main(
                 error: None,
             });
         }
+        #[cfg(feature = "trace-server")]
         "http" => {
             let (addr_tx, addr_rx) = tokio::sync::oneshot::channel();
             let t = tokio::spawn(async move {
@@ -228,6 +231,7 @@ async fn trace_main(
 
 // todo: reuse code from tools preview
 /// Create a http server for the trace program.
+#[cfg(feature = "trace-server")]
 pub async fn make_http_server(
     timings: Vec<u8>,
     static_file_addr: String,
@@ -16,7 +16,7 @@ use lsp_types::notification::Notification;
 use parking_lot::Mutex;
 use reflexo_typst::debug_loc::SourceSpanOffset;
 use reflexo_typst::{error::prelude::*, Error};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use sync_lsp::just_ok;
 use tinymist_assets::TYPST_PREVIEW_HTML;
@@ -348,7 +348,18 @@ impl PreviewState {
                         log::warn!("PreviewTask({tid}): is sending SyncEditorChanges in lsp mode");
                     }
                     EditorScrollTo(s) => client.send_notification::<ScrollSource>(&s),
-                    Outline(s) => client.send_notification::<NotifDocumentOutline>(&s),
+                    Outline(data) => {
+                        client.send_notification::<NotifDocumentOutline>(&TaskOutline {
+                            task_id: tid.clone(),
+                            data,
+                        })
+                    }
+                    OutlineDiff(data) => {
+                        client.send_notification::<NotifDocumentOutlineDiff>(&TaskOutline {
+                            task_id: tid.clone(),
+                            data,
+                        })
+                    }
                 }
             }
 
@@ -782,13 +793,30 @@ impl Notification for ScrollSource {
     const METHOD: &'static str = "tinymist/preview/scrollSource";
 }
 
+/// The outline (or the outline diff) of a preview task. The task id is sent
+/// along since a diff only applies to the outline of the same task.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskOutline<T> {
+    task_id: String,
+    #[serde(flatten)]
+    data: T,
+}
+
 struct NotifDocumentOutline;
 
 impl Notification for NotifDocumentOutline {
-    type Params = typst_preview::Outline;
+    type Params = TaskOutline<typst_preview::Outline>;
     const METHOD: &'static str = "tinymist/documentOutline";
 }
 
+struct NotifDocumentOutlineDiff;
+
+impl Notification for NotifDocumentOutlineDiff {
+    type Params = TaskOutline<typst_preview::OutlineDiff>;
+    const METHOD: &'static str = "tinymist/documentOutlineDiff";
+}
+
 /// Find the output location in the document for a cursor position.
 fn jump_from_cursor(document: &TypstDocument, source: &Source, cursor: usize) -> Vec<Position> {
     let Some(node) = LinkedNode::new(source.root())
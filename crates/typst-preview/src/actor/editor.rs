@@ -7,7 +7,7 @@ use tokio::sync::mpsc;
 
 use crate::actor::render::RenderActorRequest;
 use crate::debug_loc::{InternQuery, SpanInterner};
-use crate::outline::Outline;
+use crate::outline::{Outline, OutlineDiff};
 use crate::{
     ChangeCursorPositionRequest, DocToSrcJumpInfo, EditorServer, MemoryFiles, MemoryFilesShort,
     ResolveSourceLocRequest,
@@ -39,6 +39,7 @@ pub enum EditorActorRequest {
     DocToSrcJumpResolve(DocToSrcJumpResolveRequest),
     DocToSrcJump(DocToSrcJumpInfo),
     Outline(Outline),
+    OutlineDiff(OutlineDiff),
     CompileStatus(CompileStatus),
 }
 
@@ -145,6 +146,8 @@ pub enum ControlPlaneResponse {
     CompileStatus(CompileStatus),
     #[serde(rename = "outline")]
     Outline(Outline),
+    #[serde(rename = "outlineDiff")]
+    OutlineDiff(OutlineDiff),
 }
 
 impl<T: EditorServer> EditorActor<T> {
@@ -196,6 +199,9 @@ impl<T: EditorServer> EditorActor<T> {
                         EditorActorRequest::Outline(outline) => {
                             self.editor_conn.resp_ctl_plane("Outline", ControlPlaneResponse::Outline(outline)).await
                         }
+                        EditorActorRequest::OutlineDiff(diff) => {
+                            self.editor_conn.resp_ctl_plane("OutlineDiff", ControlPlaneResponse::OutlineDiff(diff)).await
+                        }
                     };
 
                     if !sent {
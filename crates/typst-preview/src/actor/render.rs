@@ -13,6 +13,11 @@ use crate::debug_loc::SpanInterner;
 use crate::outline::Outline;
 use crate::{ChangeCursorPositionRequest, CompileView, DocToSrcJumpInfo, ResolveSourceLocRequest};
 
+/// The number of outlines rendered between two resets of the span interner.
+/// The outline is sent in full after a reset, since the spans of the items
+/// are interned again.
+const OUTLINE_RESET_INTERVAL: usize = 16;
+
 #[derive(Debug, Clone)]
 pub struct ResolveSpanRequest(pub Vec<ElementPoint>);
 
@@ -293,6 +298,12 @@ pub struct OutlineRenderActor {
     editor_tx: mpsc::UnboundedSender<EditorActorRequest>,
 
     span_interner: SpanInterner,
+    /// Whether to send the changes of the outline instead of the full outline.
+    outline_diff: bool,
+    /// The last sent outline, against which the next outline is diffed.
+    last_outline: Option<Outline>,
+    /// The number of rendered outlines.
+    generation: usize,
 }
 
 impl OutlineRenderActor {
@@ -301,12 +312,16 @@ impl OutlineRenderActor {
         document: Arc<parking_lot::RwLock<Option<Arc<dyn CompileView>>>>,
         editor_tx: mpsc::UnboundedSender<EditorActorRequest>,
         span_interner: SpanInterner,
+        outline_diff: bool,
     ) -> Self {
         Self {
             signal,
             document,
             editor_tx,
             span_interner,
+            outline_diff,
+            last_outline: None,
+            generation: 0,
         }
     }
 
@@ -333,9 +348,25 @@ impl OutlineRenderActor {
                 log::info!("OutlineRenderActor: document is not ready");
                 continue;
             };
-            let data = self.outline(&document).await;
-            log::debug!("OutlineRenderActor: sending outline");
-            let Ok(_) = self.editor_tx.send(EditorActorRequest::Outline(data)) else {
+            let reset = !self.outline_diff || self.generation % OUTLINE_RESET_INTERVAL == 0;
+            self.generation += 1;
+            let data = self.outline(&document, reset).await;
+            let req = match self.last_outline.as_ref() {
+                Some(last) if !reset => {
+                    let diff = last.diff(&data);
+                    if diff.is_empty() {
+                        continue;
+                    }
+                    log::debug!("OutlineRenderActor: sending outline diff");
+                    EditorActorRequest::OutlineDiff(diff)
+                }
+                _ => {
+                    log::debug!("OutlineRenderActor: sending outline");
+                    EditorActorRequest::Outline(data.clone())
+                }
+            };
+            self.last_outline = Some(data);
+            let Ok(_) = self.editor_tx.send(req) else {
                 log::info!("OutlineRenderActor: outline_sender is dropped");
                 break;
             };
@@ -343,10 +374,14 @@ impl OutlineRenderActor {
         log::info!("OutlineRenderActor: exiting")
     }
 
-    async fn outline(&self, document: &TypstDocument) -> Outline {
+    /// Renders the outline. The spans interned since the last reset keep
+    /// their ids, so that the unchanged items are not diffed as changed.
+    async fn outline(&self, document: &TypstDocument, reset: bool) -> Outline {
         self.span_interner
             .with_writer(|interner| {
-                interner.reset();
+                if reset {
+                    interner.reset();
+                }
                 crate::outline::outline(interner, document)
             })
             .await
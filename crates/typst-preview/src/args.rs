@@ -75,4 +75,9 @@ pub struct PreviewArgs {
     /// Used by lsp for controlling the preview refresh style.
     #[cfg_attr(feature = "clap", clap(long, default_value = "onType", hide(true)))]
     pub refresh_style: RefreshStyle,
+
+    /// Used by lsp for sending the changes of the outline instead of the full
+    /// outline, which the client must apply to the last received outline.
+    #[cfg_attr(feature = "clap", clap(long = "outline-diff", hide(true)))]
+    pub outline_diff: bool,
 }
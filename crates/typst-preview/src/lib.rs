@@ -7,7 +7,7 @@ pub use actor::editor::{
     CompileStatus, ControlPlaneMessage, ControlPlaneResponse, ControlPlaneRx, ControlPlaneTx,
 };
 pub use args::*;
-pub use outline::Outline;
+pub use outline::{Outline, OutlineDiff};
 
 use std::{collections::HashMap, future::Future, path::PathBuf, pin::Pin, sync::Arc};
 
@@ -133,6 +133,7 @@ impl Previewer {
                     h.doc_sender.clone(),
                     h.editor_tx.clone(),
                     h.span_interner,
+                    h.outline_diff,
                 );
                 tokio::spawn(outline_render_actor.run());
 
@@ -263,6 +264,7 @@ impl PreviewBuilder {
             invert_colors: arguments.invert_colors.clone(),
             renderer_tx: renderer_mailbox.0.clone(),
             enable_partial_rendering: arguments.enable_partial_rendering,
+            outline_diff: arguments.outline_diff,
             doc_sender,
         };
 
@@ -433,6 +435,7 @@ struct DataPlane {
     webview_tx: broadcast::Sender<WebviewActorRequest>,
     editor_tx: mpsc::UnboundedSender<EditorActorRequest>,
     enable_partial_rendering: bool,
+    outline_diff: bool,
     invert_colors: String,
     renderer_tx: broadcast::Sender<RenderActorRequest>,
     doc_sender: Arc<parking_lot::RwLock<Option<Arc<dyn CompileView>>>>,
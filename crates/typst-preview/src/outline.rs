@@ -1,7 +1,9 @@
+use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
 
 use reflexo_typst::debug_loc::DocumentPosition;
 use serde::{Deserialize, Serialize};
+use tinymist_std::hash::hash64;
 use tinymist_std::typst::TypstDocument;
 use typst::foundations::{Content, NativeElement, Packed, StyleChain};
 use typst::introspection::Introspector;
@@ -129,6 +131,9 @@ pub struct Outline {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct OutlineItem {
+    /// The stable id of the item, which is derived from the span of the
+    /// heading and kept across compilations.
+    id: String,
     /// Plain text title.
     title: String,
     /// Span id in hex-format.
@@ -139,26 +144,100 @@ struct OutlineItem {
     children: Vec<OutlineItem>,
 }
 
+/// The changes of the outline between two compilations, so that frontends can
+/// update the outline without rebuilding it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OutlineDiff {
+    /// The ids of the removed items.
+    removed: Vec<String>,
+    /// The added or changed items, in which a parent always precedes its
+    /// children.
+    changed: Vec<OutlineItemPatch>,
+}
+
+/// An outline item without children, which is located by its parent and
+/// index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct OutlineItemPatch {
+    /// The stable id of the item.
+    id: String,
+    /// The id of the parent item, or `None` for a top-level item.
+    parent: Option<String>,
+    /// The index of the item among its siblings.
+    index: usize,
+    /// Plain text title.
+    title: String,
+    /// Span id in hex-format.
+    span: Option<String>,
+    /// The resolved position in the document.
+    position: Option<DocumentPosition>,
+}
+
+impl Outline {
+    /// Computes the changes from this outline to the `next` one.
+    pub fn diff(&self, next: &Outline) -> OutlineDiff {
+        let (mut prev_items, mut next_items) = (vec![], vec![]);
+        flatten(&self.items, None, &mut prev_items);
+        flatten(&next.items, None, &mut next_items);
+
+        let prev: HashMap<_, _> = prev_items.iter().map(|e| (e.id.as_str(), e)).collect();
+        let next_ids: HashSet<_> = next_items.iter().map(|e| e.id.as_str()).collect();
+
+        let removed = prev_items.iter().map(|e| &e.id);
+        let removed = removed.filter(|id| !next_ids.contains(id.as_str()));
+        let changed = next_items
+            .iter()
+            .filter(|e| prev.get(e.id.as_str()) != Some(e));
+
+        OutlineDiff {
+            removed: removed.cloned().collect(),
+            changed: changed.cloned().collect(),
+        }
+    }
+}
+
+impl OutlineDiff {
+    /// Whether the outline is not changed.
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+fn flatten(items: &[OutlineItem], parent: Option<&String>, res: &mut Vec<OutlineItemPatch>) {
+    for (index, item) in items.iter().enumerate() {
+        res.push(OutlineItemPatch {
+            id: item.id.clone(),
+            parent: parent.cloned(),
+            index,
+            title: item.title.clone(),
+            span: item.span.clone(),
+            position: item.position,
+        });
+        flatten(&item.children, Some(&item.id), res);
+    }
+}
+
 pub fn outline(interner: &mut SpanInternerImpl, document: &TypstDocument) -> Outline {
     let outline = get_outline(document.introspector());
     let mut items = Vec::with_capacity(outline.as_ref().map_or(0, Vec::len));
+    let mut ids = HashMap::new();
 
     for heading in outline.iter().flatten() {
-        outline_item(interner, heading, &mut items);
+        outline_item(interner, &mut ids, heading, &mut items);
     }
 
     Outline { items }
 }
 
-fn outline_item(interner: &mut SpanInternerImpl, src: &HeadingNode, res: &mut Vec<OutlineItem>) {
+fn outline_item(
+    interner: &mut SpanInternerImpl,
+    ids: &mut HashMap<u64, usize>,
+    src: &HeadingNode,
+    res: &mut Vec<OutlineItem>,
+) {
     let body = src.body.clone();
     let title = body.plain_text().trim().to_owned();
 
-    let mut children = Vec::with_capacity(src.children.len());
-    for child in src.children.iter() {
-        outline_item(interner, child, &mut children);
-    }
-
     // use body's span first, otherwise use the element's span.
     let span = src.span;
     let span = if span.is_detached() {
@@ -167,12 +246,138 @@ fn outline_item(interner: &mut SpanInternerImpl, src: &HeadingNode, res: &mut Ve
         span
     };
 
+    let id = item_id(ids, span, &title, src.level);
+
+    let mut children = Vec::with_capacity(src.children.len());
+    for child in src.children.iter() {
+        outline_item(interner, ids, child, &mut children);
+    }
+
     let span = interner.intern(span);
 
     res.push(OutlineItem {
+        id,
         title,
         span: Some(span.to_hex()),
         position: Some(src.position),
         children,
     });
 }
+
+/// Gets the stable id of an outline item from the span of the heading, or
+/// from its title and level if the heading is detached.
+fn item_id(ids: &mut HashMap<u64, usize>, span: Span, title: &str, level: NonZeroUsize) -> String {
+    // The headings created by the same syntax, e.g. in a loop, are told apart
+    // by the order of occurrence.
+    let fingerprint = if span.is_detached() {
+        hash64(&(title, level))
+    } else {
+        hash64(&span)
+    };
+    let occurrence = ids.entry(fingerprint).or_insert(0);
+    let id = match *occurrence {
+        0 => format!("{fingerprint:x}"),
+        n => format!("{fingerprint:x}-{n}"),
+    };
+    *occurrence += 1;
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::syntax::{FileId, VirtualPath};
+
+    use super::*;
+
+    fn item(id: &str, title: &str, children: Vec<OutlineItem>) -> OutlineItem {
+        OutlineItem {
+            id: id.to_owned(),
+            title: title.to_owned(),
+            span: None,
+            position: None,
+            children,
+        }
+    }
+
+    fn sample() -> Outline {
+        Outline {
+            items: vec![
+                item("a", "Intro", vec![item("a1", "Motivation", vec![])]),
+                item("b", "Method", vec![]),
+            ],
+        }
+    }
+
+    fn changed_ids(diff: &OutlineDiff) -> Vec<(&str, Option<&str>, usize)> {
+        let changed = diff.changed.iter();
+        changed
+            .map(|e| (e.id.as_str(), e.parent.as_deref(), e.index))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_unchanged() {
+        let diff = sample().diff(&sample());
+        assert!(diff.is_empty(), "{diff:?}");
+    }
+
+    #[test]
+    fn test_diff_reordered() {
+        let mut next = sample();
+        next.items.reverse();
+
+        let diff = sample().diff(&next);
+        assert!(diff.removed.is_empty(), "{diff:?}");
+        assert_eq!(changed_ids(&diff), [("b", None, 0), ("a", None, 1)]);
+    }
+
+    #[test]
+    fn test_diff_removed() {
+        let mut next = sample();
+        next.items.remove(0);
+
+        let diff = sample().diff(&next);
+        assert_eq!(diff.removed, ["a", "a1"]);
+        assert_eq!(changed_ids(&diff), [("b", None, 0)]);
+
+        let mut next = sample();
+        next.items[0].children.clear();
+        next.items[1].title = "Methods".to_owned();
+
+        let diff = sample().diff(&next);
+        assert_eq!(diff.removed, ["a1"]);
+        assert_eq!(changed_ids(&diff), [("b", None, 1)]);
+        assert_eq!(diff.changed[0].title, "Methods");
+    }
+
+    #[test]
+    fn test_item_id() {
+        let id = FileId::new(None, VirtualPath::new("main.typ"));
+        let level = NonZeroUsize::new(1).unwrap();
+        let (first, second) = (Span::from_range(id, 0..5), Span::from_range(id, 6..10));
+
+        // The ids are kept across compilations.
+        let ids = |spans: &[Span]| {
+            let mut occurrences = HashMap::new();
+            let ids = spans
+                .iter()
+                .map(|span| item_id(&mut occurrences, *span, "A", level));
+            ids.collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&[first, second]), ids(&[first, second]));
+        assert_ne!(ids(&[first])[0], ids(&[second])[0]);
+
+        // The headings of the same span are told apart by the occurrence.
+        let duplicated = ids(&[first, first]);
+        assert_eq!(duplicated[1], format!("{}-1", duplicated[0]));
+
+        // The detached headings are identified by their titles and levels.
+        let mut occurrences = HashMap::new();
+        let detached = Span::detached();
+        let a = item_id(&mut occurrences, detached, "A", level);
+        let b = item_id(&mut occurrences, detached, "B", level);
+        let a2 = item_id(&mut occurrences, detached, "A", level.saturating_add(1));
+        assert_ne!(a, b);
+        assert_ne!(a, a2);
+    }
+}
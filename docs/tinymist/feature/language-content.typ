@@ -48,6 +48,11 @@ Language service (LSP) features:
   - #kbd("Enter") in the middle or after a trailing space in `//` inserts `//`
   - #kbd("Enter") inside `//!` doc comments automatically inserts `//!`
   - #kbd("Enter") inside equation markups automatically inserts indents.
+- `tinymist/batch`
+  - Runs multiple requests, e.g. hover, definition and references, on a single snapshot in one round trip.
+- `tinymist/pinSnapshot`, `tinymist/snapshotQuery` and `tinymist/releaseSnapshot`
+  - Pins a snapshot and runs follow-up requests on it, e.g. previewing and applying a rename, without racing against changes in between.
+  - A snapshot not used for a minute, or pinned before the projects are reloaded, is released automatically.
 
 Extra features:
 
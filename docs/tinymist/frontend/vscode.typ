@@ -192,6 +192,7 @@ Supported arguments:
 - `--input`: Add a string key-value pair visible through `sys.inputs`.
 - `--font-path` (environment variable: `TYPST_FONT_PATHS`), Font paths, maybe overridden by `tinymist.fontPaths`.
 - `--ignore-system-fonts`: Ensures system fonts won’t be searched, maybe overridden by `tinymist.systemFonts`.
+- `--font-fallback-latin`, `--font-fallback-cjk`, `--font-fallback-emoji`: The font families used when the document doesn't specify fonts, maybe overridden by `tinymist.fontFallback`.
 - `--creation-timestamp` (environment variable: `SOURCE_DATE_EPOCH`): The document’s creation date formatted as a #link("https://reproducible-builds.org/specs/source-date-epoch/")[UNIX timestamp];.
 - `--cert` (environment variable: `TYPST_CERT`): Path to CA certificate file for network access, especially for downloading typst packages.
 
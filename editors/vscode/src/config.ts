@@ -28,6 +28,7 @@ const STR_VARIABLES = [
 ];
 const STR_ARR_VARIABLES = ["fontPaths", "tinymist.fontPaths"];
 const COLOR_THEME = ["colorTheme", "tinymist.colorTheme"];
+const PLUGIN = ["plugin", "tinymist.plugin"];
 
 // todo: documentation that, typstExtraArgs won't get variable extended
 export function substVscodeVarsInConfig(
@@ -42,6 +43,10 @@ export function substVscodeVarsInConfig(
     if (COLOR_THEME.includes(k)) {
       return determineVscodeTheme();
     }
+    if (PLUGIN.includes(k)) {
+      // Plugins are only executed in a trusted workspace.
+      return { ...(value as object | undefined), trusted: vscode.workspace.isTrusted };
+    }
     if (STR_VARIABLES.includes(k)) {
       return substVscodeVars(value as string);
     }
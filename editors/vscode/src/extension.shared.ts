@@ -102,6 +102,12 @@ export async function tinymistActivate(
   if (extensionState.features.lsp) {
     tinymist.initClient(config);
   }
+  // Reloads the configuration to execute plugins once the workspace is trusted
+  context.subscriptions.push(
+    vscode.workspace.onDidGrantWorkspaceTrust(() => {
+      tinymist.client?.sendNotification("workspace/didChangeConfiguration", { settings: null });
+    }),
+  );
   // Register Shared commands
   context.subscriptions.push(
     commands.registerCommand("tinymist.onEnter", onEnterHandler),
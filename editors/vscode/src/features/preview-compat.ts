@@ -11,6 +11,8 @@ import {
   contentPreviewProvider,
   openPreviewInWebView,
   previewProcessOutline,
+  previewProcessOutlineDiff,
+  previewDisposeOutline,
 } from "./preview";
 import { tinymist } from "../lsp";
 import { loadHTMLFile } from "../util";
@@ -480,6 +482,8 @@ export class Addon2Server {
     activeEditor: vscode.TextEditor,
   ) {
     const conn = new WebSocket(`ws://127.0.0.1:${controlPlanePort}`);
+    // Each server has its own control plane, whose port identifies the outline of the preview.
+    this.disposes.push({ dispose: () => previewDisposeOutline(controlPlanePort) });
     conn.addEventListener("message", async (message) => {
       const data = JSON.parse(message.data as string);
       switch (data.event) {
@@ -492,7 +496,11 @@ export class Addon2Server {
           break;
         }
         case "outline": {
-          previewProcessOutline(data);
+          previewProcessOutline(controlPlanePort, data);
+          break;
+        }
+        case "outlineDiff": {
+          previewProcessOutlineDiff(controlPlanePort, data);
           break;
         }
         default: {
@@ -328,6 +328,7 @@ async function launchPreviewLsp(task: LaunchInBrowserTask | LaunchInWebViewTask)
 
   const disposes = new DisposeList();
   registerPreviewTaskDispose(taskId, disposes);
+  disposes.add(() => previewDisposeOutline(taskId));
 
   const { dataPlanePort, staticServerPort, isPrimary } = await invokeLspCommand();
   if (!dataPlanePort || !staticServerPort) {
@@ -400,6 +401,8 @@ async function launchPreviewLsp(task: LaunchInBrowserTask | LaunchInWebViewTask)
       taskId,
       "--refresh-style",
       refreshStyle,
+      // The outline is updated by `previewProcessOutlineDiff`.
+      "--outline-diff",
       ...dataPlaneHostArgs,
       ...partialRenderingArgs,
       ...invertColorsArgs,
@@ -538,11 +541,65 @@ export enum ScrollSyncModeEnum {
 
 export type ScrollSyncMode = "never" | "onSelectionChangeByMouse" | "onSelectionChange";
 
-export function previewProcessOutline(outlineData: any) {
+/**
+ * The last outline of each preview task, to which the outline diffs of the task are applied.
+ */
+const taskOutlines = new Map<string, { items: OutlineItemData[] }>();
+
+export function previewProcessOutline(taskId: string, outlineData: any) {
+  taskOutlines.set(taskId, outlineData);
   contentPreviewProvider.then((p) => p.postOutlineItem(outlineData /* Outline */));
   outlineProvider.then((p) => p.postOutlineItem(outlineData /* Outline */));
 }
 
+export function previewProcessOutlineDiff(taskId: string, diff: OutlineDiff) {
+  const outline = taskOutlines.get(taskId);
+  if (!outline) {
+    console.warn(`received outline diff before the outline of task ${taskId}`, diff);
+    return;
+  }
+  previewProcessOutline(taskId, applyOutlineDiff(outline, diff));
+}
+
+export function previewDisposeOutline(taskId: string) {
+  taskOutlines.delete(taskId);
+}
+
+/**
+ * Applies the changes to the outline. The items are identified by their stable ids, so the
+ * frontends keep the expanded/collapsed state of the unchanged items.
+ */
+function applyOutlineDiff(outline: { items: OutlineItemData[] }, diff: OutlineDiff) {
+  const patches = new Map<string, OutlineItemPatch>();
+  const flatten = (items: OutlineItemData[], parent?: string) => {
+    items.forEach((item, index) => {
+      const { children, ...data } = item;
+      patches.set(item.id!, { ...data, id: item.id!, parent, index });
+      flatten(children, item.id);
+    });
+  };
+  flatten(outline.items);
+  for (const id of diff.removed) {
+    patches.delete(id);
+  }
+  for (const patch of diff.changed) {
+    patches.set(patch.id, patch);
+  }
+
+  const nodes = new Map<string, OutlineItemData>();
+  for (const { parent: _parent, index: _index, ...data } of patches.values()) {
+    nodes.set(data.id, { ...data, children: [] });
+  }
+  const items: OutlineItemData[] = [];
+  const sorted = [...patches.values()].sort((x, y) => x.index - y.index);
+  for (const patch of sorted) {
+    const siblings = patch.parent ? nodes.get(patch.parent)?.children : items;
+    siblings?.push(nodes.get(patch.id)!);
+  }
+
+  return { items };
+}
+
 class ContentPreviewProvider implements vscode.WebviewViewProvider {
   private _view?: vscode.WebviewView;
 
@@ -660,12 +717,27 @@ interface CursorPosition {
 }
 
 interface OutlineItemData {
+  id?: string;
   title: string;
   span?: string;
   position?: CursorPosition;
   children: OutlineItemData[];
 }
 
+interface OutlineItemPatch {
+  id: string;
+  parent?: string | null;
+  index: number;
+  title: string;
+  span?: string;
+  position?: CursorPosition;
+}
+
+interface OutlineDiff {
+  removed: string[];
+  changed: OutlineItemPatch[];
+}
+
 class OutlineProvider implements vscode.TreeDataProvider<OutlineItem> {
   constructor(private readonly _extensionUri: vscode.Uri) {}
 
@@ -719,6 +791,8 @@ export class OutlineItem extends vscode.TreeItem {
     },
   ) {
     super(data.title, collapsibleState);
+    // Keeps the expanded/collapsed state across updates of the outline.
+    this.id = data.id;
     const span = this.data.span;
     let detachedHint = span ? `` : `, detached`;
 
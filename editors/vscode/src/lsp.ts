@@ -16,7 +16,7 @@ import { extensionState } from "./state";
 import { DisposeList, getSensibleTextEditorColumn, typstDocumentSelector } from "./util";
 import { substVscodeVarsInConfig } from "./config";
 import { wordCountItemProcess } from "./ui-extends";
-import { previewProcessOutline } from "./features/preview";
+import { previewProcessOutline, previewProcessOutlineDiff } from "./features/preview";
 
 interface ResourceRoutes {
   "/fonts": any;
@@ -431,7 +431,12 @@ export class LanguageState {
 
     // (Optional) The server requests to update the document outline
     client.onNotification("tinymist/documentOutline", async (data: any) => {
-      previewProcessOutline(data);
+      const { taskId, ...outline } = data;
+      previewProcessOutline(taskId, outline);
+    });
+    client.onNotification("tinymist/documentOutlineDiff", async (data: any) => {
+      const { taskId, ...diff } = data;
+      previewProcessOutlineDiff(taskId, diff);
     });
   }
 
@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use lsp_types::{DocumentChangeOperation, DocumentChanges, Position, Range, Url, WorkspaceEdit};
+use serde_json::json;
+use tinymist::headless::HeadlessClient;
+
+fn workspace(files: &[(&str, &str)]) -> tempfile::TempDir {
+    let root = tempfile::tempdir().unwrap();
+    for (path, content) in files {
+        std::fs::write(root.path().join(path), content).unwrap();
+    }
+    root
+}
+
+fn wait_file(path: &Path) {
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while !path.exists() {
+        assert!(Instant::now() < deadline, "timeout waiting for {path:?}");
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn edited_files(edit: WorkspaceEdit) -> HashSet<Url> {
+    let mut files = HashSet::new();
+    files.extend(
+        edit.changes
+            .into_iter()
+            .flat_map(|changes| changes.into_keys()),
+    );
+    match edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => {
+            files.extend(edits.into_iter().map(|e| e.text_document.uri));
+        }
+        Some(DocumentChanges::Operations(ops)) => {
+            files.extend(ops.into_iter().filter_map(|op| match op {
+                DocumentChangeOperation::Edit(e) => Some(e.text_document.uri),
+                DocumentChangeOperation::Op(..) => None,
+            }));
+        }
+        None => {}
+    }
+    files
+}
+
+#[test]
+fn diagnostics_follow_edits() {
+    let root = workspace(&[]);
+    let mut client = HeadlessClient::start(root.path(), json!({}));
+
+    client.open_file("main.typ", "#let x = 1;\n#y");
+    let diagnostics = client.expect_diagnostics("main.typ");
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+
+    client.edit(
+        "main.typ",
+        Range::new(Position::new(1, 1), Position::new(1, 2)),
+        "x",
+    );
+    let diagnostics = client.expect_diagnostics("main.typ");
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+
+    client.shutdown();
+}
+
+#[test]
+fn syntax_errors_tagged_by_stage() {
+    let root = workspace(&[]);
+    let mut client = HeadlessClient::start(root.path(), json!({}));
+
+    client.open_file("main.typ", "#let x = (1, 2");
+    let diagnostics = client.expect_diagnostics("main.typ");
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert_eq!(diagnostics[0].data, Some(json!({ "stage": "syntax" })));
+
+    client.edit(
+        "main.typ",
+        Range::new(Position::new(0, 14), Position::new(0, 14)),
+        ")\n#y",
+    );
+    let compile_stage = Some(json!({ "stage": "compile" }));
+    let diagnostics = client.wait_diagnostics("main.typ", |diagnostics| {
+        diagnostics.iter().any(|d| d.data == compile_stage)
+    });
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert_eq!(diagnostics[0].data, compile_stage);
+    assert!(diagnostics[0].message.contains("unknown variable: y"));
+
+    client.shutdown();
+}
+
+#[test]
+fn rename_across_files() {
+    let lib = "#let foo = 1;\n";
+    let main = "#import \"lib.typ\": foo\n#foo\n";
+    let root = workspace(&[("lib.typ", lib), ("main.typ", main)]);
+    let mut client = HeadlessClient::start(root.path(), json!({}));
+
+    client.open_file("lib.typ", lib);
+    client.open_file("main.typ", main);
+
+    let edit = client.rename("lib.typ", Position::new(0, 5), "bar");
+    let files = edited_files(edit.expect("no rename edits"));
+    assert!(files.contains(&client.url("lib.typ")), "{files:?}");
+    assert!(files.contains(&client.url("main.typ")), "{files:?}");
+
+    client.shutdown();
+}
+
+#[test]
+fn export_on_save() {
+    let root = workspace(&[("main.typ", "= Hello")]);
+    let mut client = HeadlessClient::start(root.path(), json!({ "exportPdf": "onSave" }));
+    let pdf = root.path().join("main.pdf");
+
+    // The document is exported once the entry is focused.
+    client.open_file("main.typ", "= Hello");
+    wait_file(&pdf);
+    std::fs::remove_file(&pdf).unwrap();
+
+    client.edit(
+        "main.typ",
+        Range::new(Position::new(0, 7), Position::new(0, 7)),
+        ", World",
+    );
+    std::fs::write(root.path().join("main.typ"), "= Hello, World").unwrap();
+    client.save("main.typ");
+    wait_file(&pdf);
+
+    client.shutdown();
+}
+
+#[test]
+fn completion_in_code() {
+    let root = workspace(&[]);
+    let mut client = HeadlessClient::start(root.path(), json!({}));
+
+    client.open_file("main.typ", "#let alpha = 1;\n#al");
+    let completion = client.completion_at("main.typ", Position::new(1, 3));
+    let items = match completion.expect("no completion") {
+        lsp_types::CompletionResponse::Array(items) => items,
+        lsp_types::CompletionResponse::List(list) => list.items,
+    };
+    assert!(items.iter().any(|item| item.label == "alpha"), "{items:?}");
+
+    client.shutdown();
+}
+
+#[test]
+fn batch_queries() {
+    let root = workspace(&[]);
+    let mut client = HeadlessClient::start(root.path(), json!({}));
+
+    client.open_file("main.typ", "#let alpha = 1;\n#alpha");
+    let position = json!({
+        "textDocument": { "uri": client.url("main.typ") },
+        "position": { "line": 1, "character": 2 },
+    });
+    let references = json!({
+        "textDocument": { "uri": client.url("main.typ") },
+        "position": { "line": 1, "character": 2 },
+        "context": { "includeDeclaration": true },
+    });
+    let responses = client.batch(vec![
+        ("textDocument/hover", position.clone()),
+        ("textDocument/definition", position.clone()),
+        ("textDocument/references", references),
+        ("textDocument/semanticTokens/full", position.clone()),
+    ]);
+
+    assert_eq!(responses.len(), 4, "{responses:?}");
+    assert!(responses[0]["result"].is_object(), "{responses:?}");
+    assert!(!responses[1]["result"].is_null(), "{responses:?}");
+    assert!(responses[2]["result"].is_array(), "{responses:?}");
+    assert!(responses[3]["error"].is_object(), "{responses:?}");
+
+    // The requests on the files of another entry are not run on the entry of
+    // the first request.
+    client.open_file("other.typ", "#let beta = 2;\n#beta");
+    let other = json!({
+        "textDocument": { "uri": client.url("other.typ") },
+        "position": { "line": 1, "character": 2 },
+    });
+    let responses = client.batch(vec![
+        ("textDocument/hover", position.clone()),
+        ("textDocument/hover", other),
+    ]);
+    assert!(responses[0]["result"].is_object(), "{responses:?}");
+    assert!(responses[1]["error"].is_object(), "{responses:?}");
+
+    client.shutdown();
+}
+
+#[test]
+fn queries_on_pinned_snapshot() {
+    let root = workspace(&[]);
+    let mut client = HeadlessClient::start(root.path(), json!({}));
+
+    client.open_file("main.typ", "#let alpha = 1;\n#alpha");
+    let token = client.pin_snapshot("main.typ");
+
+    client.edit(
+        "main.typ",
+        Range::new(Position::new(0, 5), Position::new(0, 10)),
+        "beta",
+    );
+
+    let position = json!({
+        "textDocument": { "uri": client.url("main.typ") },
+        "position": { "line": 1, "character": 2 },
+    });
+    let pinned = client.snapshot_query(&token, "textDocument/definition", position.clone());
+    assert!(!pinned.is_null(), "{pinned:?}");
+    let latest = client.batch(vec![("textDocument/definition", position)]);
+    assert!(latest[0]["result"].is_null(), "{latest:?}");
+    assert!(latest[0].get("error").is_none(), "{latest:?}");
+
+    client.release_snapshot(&token);
+    client.shutdown();
+}